@@ -1,13 +1,129 @@
-use clap::Parser;
+use clap::{Args as ClapArgs, Parser, Subcommand};
 
 /// Simple program to start a server
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    /// Start the server
-    #[arg(short, long)]
+    /// Start the server, kept for compatibility with `serve`
+    #[arg(short, long, hide = true)]
     pub start: bool,
     /// Path to the .env file
-    #[arg(short, long, default_value = ".env")]
+    #[arg(short, long, default_value = ".env", global = true)]
     pub env: String,
+    /// Path to a TOML or YAML configuration file, overridden by environment variables
+    #[arg(short, long, global = true)]
+    pub config: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+impl Args {
+    /// Settings given on the command line, as `(environment variable, flag, value)`.
+    /// They take precedence over the environment and the configuration file.
+    pub fn overrides(&self) -> Vec<(&'static str, &'static str, String)> {
+        match &self.command {
+            Some(Commands::Serve(server_args)) | Some(Commands::Check(server_args)) => {
+                server_args.overrides()
+            }
+            _ => vec![],
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Start the server
+    Serve(ServerArgs),
+    /// Validate the configuration and check that Redis is reachable
+    Check(ServerArgs),
+    /// Manage tokens
+    Token {
+        #[command(subcommand)]
+        command: TokenCommands,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Send commands to a rediserve server over HTTP
+    Cli(CliArgs),
+    /// Print the version
+    Version,
+}
+
+/// Options of the HTTP client
+#[derive(ClapArgs, Debug)]
+pub struct CliArgs {
+    /// URL of the rediserve server
+    #[arg(short, long, default_value = "http://127.0.0.1:3000")]
+    pub url: String,
+    /// Token sent as a bearer token
+    #[arg(short, long)]
+    pub token: Option<String>,
+    /// Read commands from stdin, one per line, and send them as one pipeline
+    #[arg(long)]
+    pub pipe: bool,
+    /// Command to run instead of starting the interactive prompt
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    pub command: Vec<String>,
+}
+
+/// Server settings that can be given on the command line
+#[derive(ClapArgs, Debug, Default)]
+pub struct ServerArgs {
+    /// Port to listen on, overrides SERVER_PORT
+    #[arg(short, long)]
+    pub port: Option<u16>,
+    /// Address to listen on, may be repeated, overrides BIND_ADDRESS
+    #[arg(short, long)]
+    pub bind: Vec<String>,
+    /// Unix socket path to listen on, overrides UNIX_SOCKET
+    #[arg(long)]
+    pub unix_socket: Option<String>,
+    /// Redis URL, overrides REDIS_URL
+    #[arg(long)]
+    pub redis_url: Option<String>,
+    /// Token clients must send, overrides TOKEN
+    #[arg(long)]
+    pub token: Option<String>,
+}
+
+impl ServerArgs {
+    fn overrides(&self) -> Vec<(&'static str, &'static str, String)> {
+        [
+            (
+                "SERVER_PORT",
+                "--port",
+                self.port.map(|port| port.to_string()),
+            ),
+            (
+                "BIND_ADDRESS",
+                "--bind",
+                Some(self.bind.join(",")).filter(|bind| !bind.is_empty()),
+            ),
+            ("UNIX_SOCKET", "--unix-socket", self.unix_socket.clone()),
+            ("REDIS_URL", "--redis-url", self.redis_url.clone()),
+            ("TOKEN", "--token", self.token.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(name, flag, value)| value.map(|value| (name, flag, value)))
+        .collect()
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TokenCommands {
+    /// Generate a random token suitable for TOKEN or the tokens file
+    Generate {
+        /// Number of random bytes in the token
+        #[arg(long, default_value_t = 32)]
+        bytes: usize,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// Validate the configuration and report every invalid setting
+    Check,
 }
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use crate::{
+    cmd::Args,
+    config::AppConfig,
+    services::{CertificateAuth, JwtVerifier, TokenStore},
+    tls,
+    utils::redis_pool,
+};
+
+/// Validates the configuration, printing every invalid setting.
+pub fn check_config(args: &Args) -> Option<AppConfig> {
+    match AppConfig::load(args) {
+        Ok(config) => {
+            println!("Configuration is valid");
+            Some(config)
+        }
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}", error);
+            }
+            None
+        }
+    }
+}
+
+/// Validates the configuration, loads the files it references and pings Redis,
+/// returning whether everything is usable.
+pub async fn check(args: &Args) -> bool {
+    let Some(config) = check_config(args) else {
+        return false;
+    };
+
+    let mut results = vec![
+        ("Tokens", TokenStore::from_config(&config).map(|_| ())),
+        (
+            "JWT keys",
+            JwtVerifier::from_config(&config.jwt).map(|_| ()),
+        ),
+        (
+            "Client certificate identities",
+            CertificateAuth::from_config(config.tls.as_ref()).map(|_| ()),
+        ),
+    ];
+
+    if let Some(tls_config) = &config.tls {
+        results.push((
+            "TLS certificate",
+            tls::load_server_config(tls_config).map(|_| ()),
+        ));
+    }
+
+    results.push(("Redis", ping_redis(&config).await));
+
+    let mut valid = true;
+
+    for (name, result) in results {
+        match result {
+            Ok(()) => println!("{}: ok", name),
+            Err(error) => {
+                eprintln!("{}: {}", name, error);
+                valid = false;
+            }
+        }
+    }
+
+    valid
+}
+
+async fn ping_redis(config: &AppConfig) -> Result<(), String> {
+    let pool = redis_pool::connection_info(config)
+        .and_then(|connection_info| redis_pool::create_pool(connection_info, &config.redis_pool))?;
+
+    let ping = async {
+        let mut con = pool.get().await.map_err(|e| e.to_string())?;
+
+        redis::cmd("PING")
+            .query_async::<_, String>(&mut con)
+            .await
+            .map_err(|e| e.to_string())
+    };
+
+    match tokio::time::timeout(Duration::from_secs(10), ping).await {
+        Ok(result) => result.map(|_| ()),
+        Err(_) => Err("timed out connecting to Redis".to_string()),
+    }
+}
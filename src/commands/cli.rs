@@ -0,0 +1,349 @@
+use reqwest::{Client, RequestBuilder};
+use serde_json::Value;
+use tokio::io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use crate::{cmd::CliArgs, models::api_types::JsonValue};
+
+/// HTTP client for a rediserve server, sending commands the way `redis-cli` would.
+struct RediserveClient {
+    http: Client,
+    url: String,
+    token: Option<String>,
+}
+
+impl RediserveClient {
+    fn new(url: &str, token: Option<String>) -> Self {
+        RediserveClient {
+            http: Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+            token,
+        }
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        let request = self.http.post(format!("{}{}", self.url, path));
+
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn send(&self, path: &str, body: Value) -> Result<Value, String> {
+        let response = self
+            .post(path)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let status = response.status();
+        let text = response.text().await.map_err(|e| e.to_string())?;
+
+        // errors such as 401 or 429 carry an `{"error": ...}` body like command errors
+        serde_json::from_str(&text).map_err(|_| format!("HTTP {}: {}", status, text.trim()))
+    }
+
+    /// Runs a single command with `POST /`.
+    async fn command(&self, args: &[String]) -> Result<Value, String> {
+        self.send("/", serde_json::json!(args)).await
+    }
+
+    /// Runs commands concurrently with `POST /pipeline`.
+    async fn pipeline(&self, commands: &[Vec<String>]) -> Result<Value, String> {
+        self.send("/pipeline", serde_json::json!(commands)).await
+    }
+
+    /// Runs commands atomically with `POST /multi-exec`.
+    async fn transaction(&self, commands: &[Vec<String>]) -> Result<Value, String> {
+        let response = self
+            .send("/multi-exec", serde_json::json!(commands))
+            .await?;
+
+        Ok(match response {
+            Value::Object(mut object) => match object.remove("TransactionResponse") {
+                Some(responses) => responses,
+                None => object
+                    .remove("TransactionError")
+                    .unwrap_or(Value::Object(object)),
+            },
+            response => response,
+        })
+    }
+}
+
+/// Splits a command line into arguments like `redis-cli`: whitespace separated, with
+/// double quotes supporting `\n`, `\r`, `\t`, `\"`, `\\` and `\xHH` escapes and single quotes taken literally.
+pub fn split_args(line: &str) -> Result<Vec<String>, String> {
+    let mut args = vec![];
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let Some(&first) = chars.peek() else {
+            return Ok(args);
+        };
+
+        let mut arg = String::new();
+
+        if first == '"' || first == '\'' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    None => return Err("unbalanced quotes".to_string()),
+                    Some(c) if c == first => break,
+                    Some('\\') if first == '"' => match chars.next() {
+                        Some('n') => arg.push('\n'),
+                        Some('r') => arg.push('\r'),
+                        Some('t') => arg.push('\t'),
+                        Some('x') => {
+                            let hex: String = chars.by_ref().take(2).collect();
+                            match u8::from_str_radix(&hex, 16) {
+                                Ok(byte) if hex.len() == 2 => arg.push(byte as char),
+                                _ => return Err(format!("invalid escape \\x{}", hex)),
+                            }
+                        }
+                        Some(c) => arg.push(c),
+                        None => return Err("unbalanced quotes".to_string()),
+                    },
+                    Some('\\') if first == '\'' && chars.peek() == Some(&'\'') => {
+                        arg.push(chars.next().unwrap())
+                    }
+                    Some(c) => arg.push(c),
+                }
+            }
+
+            // a closing quote must be followed by a space or the end of the line
+            if chars.peek().is_some_and(|c| !c.is_whitespace()) {
+                return Err("closing quote must be followed by a space".to_string());
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                arg.push(c);
+            }
+        }
+
+        args.push(arg);
+    }
+}
+
+/// Formats a reply the way `redis-cli` prints it.
+pub fn format_reply(response: &JsonValue) -> String {
+    match response.get("error") {
+        Some(error) => format!("(error) {}", error.as_str().unwrap_or_default()),
+        None => format_value(response.get("result").unwrap_or(&Value::Null), 0),
+    }
+}
+
+fn format_value(value: &JsonValue, indent: usize) -> String {
+    match value {
+        Value::Null => "(nil)".to_string(),
+        Value::Number(number) => format!("(integer) {}", number),
+        Value::Bool(value) => format!("(integer) {}", *value as u8),
+        Value::String(value) => format!("{:?}", value),
+        Value::Array(items) if items.is_empty() => "(empty array)".to_string(),
+        Value::Array(items) => {
+            let width = items.len().to_string().len();
+
+            items
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    let prefix = format!("{:>width$}) ", index + 1, width = width);
+                    let item = format_value(item, indent + prefix.len());
+                    if index == 0 {
+                        format!("{}{}", prefix, item)
+                    } else {
+                        format!("{}{}{}", " ".repeat(indent), prefix, item)
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        }
+        Value::Object(_) => value.to_string(),
+    }
+}
+
+/// Formats each reply of a pipeline or transaction on its own line.
+fn format_replies(responses: &JsonValue) -> String {
+    match responses {
+        Value::Array(responses) => responses
+            .iter()
+            .map(format_reply)
+            .collect::<Vec<String>>()
+            .join("\n"),
+        response => format_reply(response),
+    }
+}
+
+/// Reads commands from stdin until EOF and sends them as a single pipeline.
+async fn run_pipe(client: &RediserveClient) -> bool {
+    let mut lines = BufReader::new(io::stdin()).lines();
+    let mut commands = vec![];
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        match split_args(&line) {
+            Ok(args) if args.is_empty() => {}
+            Ok(args) => commands.push(args),
+            Err(error) => {
+                eprintln!("Invalid argument(s) in {:?}: {}", line, error);
+                return false;
+            }
+        }
+    }
+
+    if commands.is_empty() {
+        return true;
+    }
+
+    match client.pipeline(&commands).await {
+        Ok(responses) => {
+            let errors = responses
+                .as_array()
+                .map(|responses| {
+                    responses
+                        .iter()
+                        .filter(|response| response.get("error").is_some())
+                        .count()
+                })
+                .unwrap_or(1);
+
+            println!("{}", format_replies(&responses));
+            println!("errors: {}, replies: {}", errors, commands.len());
+
+            errors == 0
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            false
+        }
+    }
+}
+
+/// Interactive prompt. `MULTI` queues commands until `EXEC` sends them to `/multi-exec`
+/// or `DISCARD` drops them.
+async fn run_repl(client: &RediserveClient, url: &str) -> bool {
+    let mut lines = BufReader::new(io::stdin()).lines();
+    let mut stdout = io::stdout();
+    let mut transaction: Option<Vec<Vec<String>>> = None;
+
+    loop {
+        let prompt = match &transaction {
+            Some(_) => format!("{}(TX)> ", url),
+            None => format!("{}> ", url),
+        };
+        stdout.write_all(prompt.as_bytes()).await.ok();
+        stdout.flush().await.ok();
+
+        let Ok(Some(line)) = lines.next_line().await else {
+            return true;
+        };
+
+        let args = match split_args(&line) {
+            Ok(args) if args.is_empty() => continue,
+            Ok(args) => args,
+            Err(error) => {
+                println!("Invalid argument(s): {}", error);
+                continue;
+            }
+        };
+
+        let name = args[0].to_uppercase();
+
+        let reply = match (name.as_str(), &mut transaction) {
+            ("QUIT" | "EXIT", _) => return true,
+            ("MULTI", Some(_)) => Ok("(error) ERR MULTI calls can not be nested".to_string()),
+            ("MULTI", None) => {
+                transaction = Some(vec![]);
+                Ok("OK".to_string())
+            }
+            ("EXEC", Some(_)) => {
+                let commands = transaction.take().unwrap_or_default();
+                client
+                    .transaction(&commands)
+                    .await
+                    .map(|responses| format_replies(&responses))
+            }
+            ("DISCARD", Some(_)) => {
+                transaction = None;
+                Ok("OK".to_string())
+            }
+            ("EXEC" | "DISCARD", None) => Ok(format!("(error) ERR {} without MULTI", name)),
+            (_, Some(commands)) => {
+                commands.push(args);
+                Ok("QUEUED".to_string())
+            }
+            (_, None) => client
+                .command(&args)
+                .await
+                .map(|response| format_reply(&response)),
+        };
+
+        match reply {
+            Ok(reply) => println!("{}", reply),
+            Err(error) => println!("(error) {}", error),
+        }
+    }
+}
+
+/// Runs the `cli` subcommand, returning whether it succeeded.
+pub async fn run(args: &CliArgs) -> bool {
+    let client = RediserveClient::new(&args.url, args.token.clone());
+
+    if args.pipe {
+        return run_pipe(&client).await;
+    }
+
+    if !args.command.is_empty() {
+        return match client.command(&args.command).await {
+            Ok(response) => {
+                println!("{}", format_reply(&response));
+                response.get("error").is_none()
+            }
+            Err(error) => {
+                eprintln!("{}", error);
+                false
+            }
+        };
+    }
+
+    run_repl(&client, &args.url).await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{format_reply, split_args};
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(
+            split_args(r#"set key "hello world\n" 'it\'s'"#).unwrap(),
+            vec!["set", "key", "hello world\n", "it's"]
+        );
+        assert_eq!(split_args("  get   key ").unwrap(), vec!["get", "key"]);
+        assert_eq!(
+            split_args(r#"set k "\x41""#).unwrap(),
+            vec!["set", "k", "A"]
+        );
+        assert!(split_args(r#"set k "open"#).is_err());
+        assert!(split_args(r#"set k "a"b"#).is_err());
+    }
+
+    #[test]
+    fn test_format_reply() {
+        assert_eq!(format_reply(&json!({"result": "OK"})), "\"OK\"");
+        assert_eq!(format_reply(&json!({"result": 3})), "(integer) 3");
+        assert_eq!(format_reply(&json!({"result": null})), "(nil)");
+        assert_eq!(
+            format_reply(&json!({"error": "ERR unknown command"})),
+            "(error) ERR unknown command"
+        );
+        assert_eq!(
+            format_reply(&json!({"result": ["a", ["b", 1]]})),
+            "1) \"a\"\n2) 1) \"b\"\n   2) (integer) 1"
+        );
+    }
+}
@@ -0,0 +1,5 @@
+// Implementations of the command line subcommands
+
+pub mod check;
+pub mod cli;
+pub mod token;
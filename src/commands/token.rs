@@ -0,0 +1,28 @@
+use base64::{engine, prelude::*};
+use rand::{rngs::OsRng, RngCore};
+
+/// Generates a URL safe token from `bytes` bytes of the operating system's secure random source.
+pub fn generate(bytes: usize) -> String {
+    let mut secret = vec![0u8; bytes.max(16)];
+    OsRng.fill_bytes(&mut secret);
+
+    engine::general_purpose::URL_SAFE_NO_PAD.encode(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generate;
+
+    #[test]
+    fn test_generate() {
+        let first = generate(32);
+
+        assert_eq!(first.len(), 43);
+        assert_ne!(first, generate(32));
+        assert!(first
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        // short tokens are padded to the 16 byte minimum
+        assert_eq!(generate(4).len(), 22);
+    }
+}
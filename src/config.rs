@@ -1,55 +1,986 @@
 // src/config.rs
 
-use std::env;
+use std::{
+    env,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
 
-use crate::cmd::Args;
+use axum::http::{HeaderName, HeaderValue, Method};
+use serde::Deserialize;
+use tracing::Level;
+
+use crate::{cmd::Args, config_file::ConfigFile};
+
+/// Commands considered dangerous unless configured otherwise through `DANGEROUS_COMMANDS`.
+/// Two-word entries match a command together with its first argument (its subcommand).
+pub const DEFAULT_DANGEROUS_COMMANDS: [&str; 11] = [
+    "FLUSHALL",
+    "FLUSHDB",
+    "KEYS",
+    "CONFIG SET",
+    "DEBUG",
+    "SHUTDOWN",
+    "MONITOR",
+    "SCRIPT FLUSH",
+    "CLIENT KILL",
+    "FUNCTION FLUSH",
+    "FUNCTION DELETE",
+];
+
+/// Commands running or loading Lua code, which may call any command including dangerous ones.
+/// They are added to the dangerous commands when `DANGEROUS_SCRIPTING` is set; calls of
+/// registered scripts and deployed functions stay allowed, see `CommandPolicy::is_dangerous`.
+pub const SCRIPTING_COMMANDS: [&str; 9] = [
+    "EVAL",
+    "EVAL_RO",
+    "EVALSHA",
+    "EVALSHA_RO",
+    "FCALL",
+    "FCALL_RO",
+    "SCRIPT LOAD",
+    "FUNCTION LOAD",
+    "FUNCTION RESTORE",
+];
+
+/// How dangerous commands are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DangerousCommandPolicy {
+    /// Dangerous commands are always rejected
+    Block,
+    /// Dangerous commands are only accepted from callers using the elevated token
+    Elevated,
+    /// Dangerous commands are treated like any other command
+    Allow,
+}
+
+/// Rate limits and quotas, each limit is disabled when unset
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitConfig {
+    /// Requests per second allowed for each token
+    pub token_requests_per_second: Option<u32>,
+    /// Commands per second allowed for each token, every pipeline entry counts
+    pub token_commands_per_second: Option<u32>,
+    /// Requests per second allowed for each client IP
+    pub ip_requests_per_second: Option<u32>,
+    /// Commands per second allowed for each client IP, every pipeline entry counts
+    pub ip_commands_per_second: Option<u32>,
+    /// Commands allowed per token and UTC day, counted in Redis
+    pub daily_command_quota: Option<u64>,
+}
+
+/// Size limits of requests and replies, each limit is disabled when unset
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestLimitsConfig {
+    /// Bytes of a request body
+    pub max_body_bytes: Option<usize>,
+    /// Commands of a pipeline or transaction
+    pub max_pipeline_commands: Option<usize>,
+    /// Arguments of a single command, its name excluded
+    pub max_command_arguments: Option<usize>,
+    /// Estimated bytes of the replies to a request, see `RequestLimits::reply_size`
+    pub max_reply_bytes: Option<usize>,
+}
+
+/// A read command whose replies are cached
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedCommandConfig {
+    /// Upper case command name
+    pub name: String,
+    /// Seconds a reply is served from the cache at most
+    pub ttl: u64,
+    /// Replies larger than this many bytes are not cached
+    pub max_entry_bytes: usize,
+}
+
+impl CachedCommandConfig {
+    /// Parses a `NAME[:ttl[:max_entry_bytes]]` entry of the `CACHE_COMMANDS` variable.
+    pub fn parse(entry: &str, ttl: u64, max_entry_bytes: usize) -> Result<Self, String> {
+        let mut parts = entry.trim().splitn(3, ':');
+
+        let name = parts.next().unwrap_or_default();
+        if name.is_empty() {
+            return Err(format!("invalid cached command {:?}", entry));
+        }
+
+        let ttl = match parts.next() {
+            Some(ttl) => ttl
+                .parse::<u64>()
+                .ok()
+                .filter(|ttl| *ttl > 0)
+                .ok_or_else(|| format!("invalid TTL for cached command {}", name))?,
+            None => ttl,
+        };
+
+        let max_entry_bytes = match parts.next() {
+            Some(max) => max
+                .parse::<usize>()
+                .map_err(|_| format!("invalid size limit for cached command {}", name))?,
+            None => max_entry_bytes,
+        };
+
+        Ok(CachedCommandConfig {
+            name: name.to_uppercase(),
+            ttl,
+            max_entry_bytes,
+        })
+    }
+}
+
+/// In-process cache of read command replies, disabled when no command is configured
+#[derive(Debug, Clone, Default)]
+pub struct CacheConfig {
+    pub commands: Vec<CachedCommandConfig>,
+    /// Bytes of replies held by the cache at most
+    pub max_bytes: usize,
+}
+
+/// Caching headers of GET path-style reads
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HttpCacheConfig {
+    /// Seconds clients may cache a read at most, and of keys without expiry.
+    /// Reads of keys without expiry must be revalidated when unset.
+    pub max_age: Option<u64>,
+    /// Whether shared caches such as CDNs may store reads, browsers only when false
+    pub public: bool,
+}
+
+/// Headers browsers may send by default: authentication, JSON bodies, reply encodings
+/// and conditional reads.
+pub const DEFAULT_CORS_HEADERS: &[&str] = &[
+    "Authorization",
+    "Content-Type",
+    "Upstash-Encoding",
+    "Rediserve-Encoding",
+    "If-None-Match",
+];
+
+/// Cross-origin requests from browsers, disabled when no origin is allowed
+#[derive(Debug, Clone, Default)]
+pub struct CorsConfig {
+    /// Origins allowed to call the server, `*` for any
+    pub allowed_origins: Vec<String>,
+    /// Methods allowed in cross-origin requests, `*` for any
+    pub allowed_methods: Vec<String>,
+    /// Request headers allowed in cross-origin requests, `*` for any
+    pub allowed_headers: Vec<String>,
+    /// Whether browsers send cookies and credentials, not allowed with any `*` wildcard
+    pub allow_credentials: bool,
+    /// Seconds browsers may cache a preflight response
+    pub max_age: u64,
+}
+
+impl CorsConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.allowed_origins.is_empty()
+    }
+
+    pub fn allows_any_origin(&self) -> bool {
+        is_wildcard(&self.allowed_origins)
+    }
+
+    pub fn allows_any_method(&self) -> bool {
+        is_wildcard(&self.allowed_methods)
+    }
+
+    pub fn allows_any_header(&self) -> bool {
+        is_wildcard(&self.allowed_headers)
+    }
+}
+
+fn is_wildcard(values: &[String]) -> bool {
+    values.iter().any(|value| value == "*")
+}
+
+/// A named static token, read from `TOKEN`, `ELEVATED_TOKEN`, `TOKENS` or the tokens file
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenConfig {
+    /// Name recorded in logs and used to key limits, never the secret itself
+    pub name: String,
+    /// The secret sent by clients
+    pub token: String,
+    /// Unix timestamp (seconds) after which the token is rejected
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// Whether the token may run dangerous commands under the elevated policy
+    #[serde(default)]
+    pub elevated: bool,
+    /// Commands the token may run, all commands when unset
+    #[serde(default)]
+    pub commands: Option<Vec<String>>,
+    /// Prefix every key used with the token must start with
+    #[serde(default)]
+    pub key_prefix: Option<String>,
+    /// Whether the token is limited to commands that do not write
+    #[serde(default)]
+    pub read_only: bool,
+    /// Redis ACL user the token's commands run as, the default connection user when unset
+    #[serde(default)]
+    pub redis_username: Option<String>,
+    /// Password of the Redis ACL user
+    #[serde(default)]
+    pub redis_password: Option<String>,
+}
+
+impl TokenConfig {
+    pub fn new(name: &str, token: &str) -> Self {
+        TokenConfig {
+            name: name.to_string(),
+            token: token.to_string(),
+            expires_at: None,
+            elevated: false,
+            commands: None,
+            key_prefix: None,
+            read_only: false,
+            redis_username: None,
+            redis_password: None,
+        }
+    }
+
+    /// Parses a `name:secret[:expires_at]` entry of the `TOKENS` variable.
+    pub fn parse(entry: &str) -> Result<Self, String> {
+        let mut parts = entry.trim().splitn(3, ':');
+
+        let (Some(name), Some(token)) = (parts.next(), parts.next()) else {
+            return Err(format!(
+                "invalid token entry {:?}, expected name:secret",
+                entry
+            ));
+        };
+
+        if name.is_empty() || token.is_empty() {
+            return Err(format!(
+                "invalid token entry {:?}, expected name:secret",
+                entry
+            ));
+        }
+
+        let expires_at = match parts.next() {
+            Some(expires_at) => Some(
+                expires_at
+                    .parse::<u64>()
+                    .map_err(|_| format!("invalid expiry for token {}", name))?,
+            ),
+            None => None,
+        };
+
+        Ok(TokenConfig {
+            expires_at,
+            ..TokenConfig::new(name, token)
+        })
+    }
+}
+
+/// JWT bearer authentication, enabled when at least one key source is set
+#[derive(Debug, Clone, Default)]
+pub struct JwtConfig {
+    /// Shared secret for HS256 tokens
+    pub secret: Option<String>,
+    /// Path to a PEM encoded public key for RS256 or EdDSA tokens
+    pub public_key_path: Option<String>,
+    /// Algorithm of the public key, RS256 or EdDSA
+    pub public_key_algorithm: String,
+    /// Path to a local JWKS file
+    pub jwks_path: Option<String>,
+    /// Accepted audiences, the `aud` claim is not checked when empty
+    pub audience: Vec<String>,
+}
+
+impl JwtConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.secret.is_some() || self.public_key_path.is_some() || self.jwks_path.is_some()
+    }
+}
+
+/// HTTPS termination, enabled when both the certificate and the key are set
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// Path to the PEM encoded certificate chain
+    pub cert_path: String,
+    /// Path to the PEM encoded private key
+    pub key_path: String,
+    /// Port of an optional plain HTTP listener redirecting to HTTPS
+    pub redirect_port: Option<u16>,
+    /// Seconds between checks of the certificate files for changes
+    pub reload_interval: u64,
+    /// Path to the PEM encoded CA bundle client certificates are verified against
+    pub client_ca_path: Option<String>,
+    /// Whether connections without a client certificate are refused
+    pub client_cert_required: bool,
+    /// Path to the JSON file mapping client certificate identities to permissions
+    pub client_identities_path: Option<String>,
+}
+
+/// Unix domain socket listener, for clients on the same host
+#[derive(Debug, Clone)]
+pub struct UnixSocketConfig {
+    /// Path of the socket file, replaced if it already exists
+    pub path: String,
+    /// Permissions of the socket file, e.g. `0o660`
+    pub mode: u32,
+}
+
+/// TLS settings for the connection to Redis, used with a `rediss://` URL
+#[derive(Debug, Clone, Default)]
+pub struct RedisTlsConfig {
+    /// Path to the PEM encoded CA bundle, the system trust store when unset
+    pub ca_path: Option<String>,
+    /// Path to the PEM encoded client certificate for mutual TLS
+    pub cert_path: Option<String>,
+    /// Path to the PEM encoded client key for mutual TLS
+    pub key_path: Option<String>,
+    /// Skips verification of the server certificate, for development only
+    pub insecure: bool,
+}
+
+/// Sizing and timeouts of the Redis connection pools, each timeout is disabled when unset
+#[derive(Debug, Clone, Default)]
+pub struct RedisPoolConfig {
+    /// Maximum number of connections per pool, deadpool's default when unset
+    pub max_size: Option<usize>,
+    /// Milliseconds a request waits for a free connection
+    pub wait_timeout: Option<u64>,
+    /// Milliseconds allowed to open a new connection
+    pub create_timeout: Option<u64>,
+    /// Milliseconds allowed to check a connection with PING before reusing it
+    pub recycle_timeout: Option<u64>,
+    /// Seconds after which a connection is closed instead of reused
+    pub max_lifetime: Option<u64>,
+    /// Milliseconds a command may run before the request fails with 504
+    pub command_timeout: Option<u64>,
+}
+
+/// Permissions granted to callers presenting a client certificate with the given identity
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClientIdentityConfig {
+    /// Subject common name, full subject or subject alternative name (DNS, URI, email or IP)
+    pub identity: String,
+    /// Name recorded in logs and used to key limits, the identity when unset
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Whether the caller may run dangerous commands under the elevated policy
+    #[serde(default)]
+    pub elevated: bool,
+    /// Commands the caller may run, all commands when unset
+    #[serde(default)]
+    pub commands: Option<Vec<String>>,
+    /// Prefix every key used by the caller must start with
+    #[serde(default)]
+    pub key_prefix: Option<String>,
+    /// Whether the caller is limited to commands that do not write
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// Output format of the logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human readable, one line per event
+    Compact,
+    /// One JSON object per event
+    Json,
+}
+
+/// Logging settings
+#[derive(Debug, Clone)]
+pub struct LoggingConfig {
+    /// Most verbose level logged
+    pub level: Level,
+    pub format: LogFormat,
+}
 
 /// Application configuration
 #[derive(Debug, Clone)]
 pub struct AppConfig {
     pub server_port: u16,
+    /// TCP addresses to listen on, none when only the Unix socket is served
+    pub server_bind: Vec<SocketAddr>,
+    pub unix_socket: Option<UnixSocketConfig>,
+    /// Seconds in-flight requests get to finish after SIGTERM or SIGINT
+    pub shutdown_grace_period: u64,
     pub redis_url: String,
+    pub redis_username: Option<String>,
+    pub redis_password: Option<String>,
+    pub redis_tls: RedisTlsConfig,
+    pub redis_pool: RedisPoolConfig,
+    /// Pool reserved for blocking commands such as BLPOP, so they cannot starve other requests
+    pub blocking_pool: RedisPoolConfig,
+    /// Milliseconds a blocking command may block, longer or unlimited timeouts are capped
+    pub max_block_time: u64,
+    /// Directory of `*.lua` files registered as named scripts at startup
+    pub scripts_dir: Option<String>,
+    /// Directory of Redis Functions libraries loaded with FUNCTION LOAD REPLACE at startup
+    pub functions_dir: Option<String>,
     pub token: Option<String>,
+    pub elevated_token: Option<String>,
+    pub tokens: Vec<TokenConfig>,
+    pub tokens_file: Option<String>,
+    pub tokens_reload_interval: u64,
+    pub allow_query_token: bool,
+    pub auth_max_failures: u32,
+    pub auth_lockout_seconds: u64,
+    pub dangerous_commands: Vec<String>,
+    pub dangerous_command_policy: DangerousCommandPolicy,
+    pub rate_limit: RateLimitConfig,
+    pub request_limits: RequestLimitsConfig,
+    pub cache: CacheConfig,
+    pub http_cache: HttpCacheConfig,
+    pub cors: CorsConfig,
+    pub jwt: JwtConfig,
+    pub tls: Option<TlsConfig>,
+    pub logging: LoggingConfig,
     pub env: String,
 }
 
 impl AppConfig {
-    /// Load and return the application configuration.
+    /// Load and return the application configuration, exiting with every problem found
+    /// if it is invalid.
     pub fn new(args: Args) -> Self {
+        match Self::load(&args) {
+            Ok(config) => config,
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("{}", error);
+                }
+                std::process::exit(1);
+            }
+        }
+    }
+
+    /// Loads the configuration from the configuration file, the .env file and the environment,
+    /// in increasing order of precedence, returning every invalid setting.
+    pub fn load(args: &Args) -> Result<Self, Vec<String>> {
         // Load environment variables from .env file
 
         dotenv::from_filename(&args.env).ok();
 
+        let file = match &args.config {
+            Some(path) => ConfigFile::load(path)?,
+            None => ConfigFile::default(),
+        };
+
+        let mut source = ConfigSource::new(args.overrides(), file);
+
         // Retrieve each configuration variable
-        let server_port = env::var("SERVER_PORT")
-            .unwrap_or_else(|_| "3000".to_string())
-            .parse::<u16>()
-            .expect("SERVER_PORT must be a number");
+        let server_port = source.number("SERVER_PORT").unwrap_or(3000);
 
-        let redis_url = match env::var("REDIS_URL") {
-            Ok(url) => url,
-            Err(_) => {
-                eprintln!("REDIS_URL not found, please set REDIS_URL variable in .env file");
-                std::process::exit(1);
+        let unix_socket = source.string("UNIX_SOCKET").map(|path| {
+            let mode = source.string("UNIX_SOCKET_MODE").map_or(0o660, |mode| {
+                u32::from_str_radix(&mode, 8)
+                    .ok()
+                    .filter(|mode| *mode <= 0o777)
+                    .unwrap_or_else(|| {
+                        let label = source.label("UNIX_SOCKET_MODE");
+                        source.error(format!("{} must be an octal mode such as 660", label));
+                        0
+                    })
+            });
+
+            UnixSocketConfig { path, mode }
+        });
+
+        // entries are `ip` or `ip:port` (`[ipv6]:port`), listening on every interface
+        // unless only a Unix socket is configured
+        let mut server_bind = vec![];
+        for entry in source.list("BIND_ADDRESS") {
+            match (entry.parse::<SocketAddr>(), entry.parse::<IpAddr>()) {
+                (Ok(addr), _) => server_bind.push(addr),
+                (_, Ok(ip)) => server_bind.push(SocketAddr::new(ip, server_port)),
+                _ => {
+                    let label = source.label("BIND_ADDRESS");
+                    source.error(format!(
+                        "{} must be a list of IP addresses or ip:port pairs, got {:?}",
+                        label, entry
+                    ));
+                }
             }
+        }
+
+        if server_bind.is_empty() && unix_socket.is_none() {
+            server_bind.push(SocketAddr::new(IpAddr::from([0, 0, 0, 0]), server_port));
+        }
+
+        let shutdown_grace_period = source.number("SHUTDOWN_GRACE_PERIOD").unwrap_or(30);
+
+        let redis_url = source.string("REDIS_URL").unwrap_or_else(|| {
+            source.error("REDIS_URL not found, please set REDIS_URL variable in .env file or redis.url in the configuration file");
+            String::new()
+        });
+
+        // ACL credentials, overriding any embedded in REDIS_URL
+        let redis_username = source.string("REDIS_USERNAME");
+        let redis_password = source.string("REDIS_PASSWORD");
+
+        let redis_tls = RedisTlsConfig {
+            ca_path: source.string("REDIS_TLS_CA_FILE"),
+            cert_path: source.string("REDIS_TLS_CERT_FILE"),
+            key_path: source.string("REDIS_TLS_KEY_FILE"),
+            insecure: source.bool("REDIS_TLS_INSECURE").unwrap_or(false),
         };
 
-        let token = match env::var("TOKEN") {
-            Ok(token) => Some(token),
-            Err(_) => {
-                eprintln!("Warning: Server is running without a token. Please set TOKEN variable in .env file to secure the server");
-                "".to_string();
+        if redis_tls.cert_path.is_some() != redis_tls.key_path.is_some() {
+            source.error(format!(
+                "{} and {} must be set together",
+                source.label("REDIS_TLS_CERT_FILE"),
+                source.label("REDIS_TLS_KEY_FILE")
+            ));
+        }
+
+        if redis_tls.insecure {
+            eprintln!(
+                "Warning: REDIS_TLS_INSECURE is set, the Redis server certificate is not verified"
+            );
+        }
+
+        // 0 disables a timeout or the maximum lifetime
+        let redis_pool = RedisPoolConfig {
+            max_size: source.number("REDIS_POOL_MAX_SIZE"),
+            wait_timeout: source.optional("REDIS_POOL_WAIT_TIMEOUT_MS", 5000),
+            create_timeout: source.optional("REDIS_POOL_CREATE_TIMEOUT_MS", 5000),
+            recycle_timeout: source.optional("REDIS_POOL_RECYCLE_TIMEOUT_MS", 1000),
+            max_lifetime: source.optional("REDIS_CONNECTION_MAX_LIFETIME", 0),
+            command_timeout: source.optional("REDIS_COMMAND_TIMEOUT_MS", 0),
+        };
+
+        if redis_pool.max_size == Some(0) {
+            source.error(format!(
+                "{} must be greater than 0",
+                source.label("REDIS_POOL_MAX_SIZE")
+            ));
+        }
+
+        let blocking_pool = RedisPoolConfig {
+            max_size: Some(source.number("BLOCKING_POOL_MAX_SIZE").unwrap_or(16)),
+            wait_timeout: source.optional("BLOCKING_POOL_WAIT_TIMEOUT_MS", 1000),
+            command_timeout: None,
+            ..redis_pool.clone()
+        };
+
+        if blocking_pool.max_size == Some(0) {
+            source.error(format!(
+                "{} must be greater than 0",
+                source.label("BLOCKING_POOL_MAX_SIZE")
+            ));
+        }
+
+        let max_block_time = source.number("MAX_BLOCK_TIME_MS").unwrap_or(30000);
+
+        if max_block_time == 0 {
+            source.error(format!(
+                "{} must be greater than 0",
+                source.label("MAX_BLOCK_TIME_MS")
+            ));
+        }
+
+        let scripts_dir = source.string("SCRIPTS_DIR");
+        let functions_dir = source.string("FUNCTIONS_DIR");
+
+        let token = source.string("TOKEN");
+
+        let elevated_token = source.string("ELEVATED_TOKEN");
+
+        let mut tokens = vec![];
+
+        if let Some(token) = &token {
+            tokens.push(TokenConfig::new("default", token));
+        }
+
+        if let Some(elevated_token) = &elevated_token {
+            tokens.push(TokenConfig {
+                elevated: true,
+                ..TokenConfig::new("elevated", elevated_token)
+            });
+        }
+
+        for entry in source.list("TOKENS") {
+            match TokenConfig::parse(&entry) {
+                Ok(token) => tokens.push(token),
+                Err(error) => source.error(format!("{}: {}", source.label("TOKENS"), error)),
+            }
+        }
+
+        let tokens_file = source.string("TOKENS_FILE");
+
+        let tokens_reload_interval = source.number("TOKENS_RELOAD_INTERVAL").unwrap_or(5);
+
+        let allow_query_token = source.bool("ALLOW_QUERY_TOKEN").unwrap_or(true);
+
+        // failed authentications per client IP before it is locked out, 0 disables the lockout
+        let auth_max_failures = source.number("AUTH_MAX_FAILURES").unwrap_or(10);
+        let auth_lockout_seconds = source.number("AUTH_LOCKOUT_SECONDS").unwrap_or(300);
+
+        let mut dangerous_commands: Vec<String> = match source.string("DANGEROUS_COMMANDS") {
+            Some(_) => source.list("DANGEROUS_COMMANDS"),
+            None => DEFAULT_DANGEROUS_COMMANDS
+                .iter()
+                .map(|command| command.to_string())
+                .collect(),
+        };
+
+        if source.bool("DANGEROUS_SCRIPTING").unwrap_or(false) {
+            dangerous_commands.extend(SCRIPTING_COMMANDS.iter().map(|command| command.to_string()));
+        }
+
+        // Without an explicit policy, an elevated token implies the elevated policy
+        let dangerous_command_policy = match source.string("DANGEROUS_COMMAND_POLICY") {
+            Some(policy) => match policy.to_lowercase().as_str() {
+                "block" => DangerousCommandPolicy::Block,
+                "elevated" => DangerousCommandPolicy::Elevated,
+                "allow" => DangerousCommandPolicy::Allow,
+                _ => {
+                    source.error(format!(
+                        "{} must be one of block, elevated or allow",
+                        source.label("DANGEROUS_COMMAND_POLICY")
+                    ));
+                    DangerousCommandPolicy::Block
+                }
+            },
+            None if elevated_token.is_some() => DangerousCommandPolicy::Elevated,
+            None => DangerousCommandPolicy::Block,
+        };
+
+        if dangerous_command_policy == DangerousCommandPolicy::Elevated && elevated_token.is_none()
+        {
+            eprintln!("Warning: DANGEROUS_COMMAND_POLICY is elevated but ELEVATED_TOKEN is not set, dangerous commands will be rejected");
+        }
+
+        let rate_limit = RateLimitConfig {
+            token_requests_per_second: source.number("TOKEN_REQUESTS_PER_SECOND"),
+            token_commands_per_second: source.number("TOKEN_COMMANDS_PER_SECOND"),
+            ip_requests_per_second: source.number("IP_REQUESTS_PER_SECOND"),
+            ip_commands_per_second: source.number("IP_COMMANDS_PER_SECOND"),
+            daily_command_quota: source.number("DAILY_COMMAND_QUOTA"),
+        };
+
+        // 0 disables a limit
+        let request_limits = RequestLimitsConfig {
+            max_body_bytes: source.optional("MAX_BODY_BYTES", 2 * 1024 * 1024),
+            max_pipeline_commands: source.optional("MAX_PIPELINE_COMMANDS", 1000),
+            max_command_arguments: source.optional("MAX_COMMAND_ARGUMENTS", 10000),
+            max_reply_bytes: source.optional("MAX_REPLY_BYTES", 16 * 1024 * 1024),
+        };
+
+        let cache_ttl = source.number("CACHE_TTL").unwrap_or(60);
+        let cache_max_entry_bytes = source.number("CACHE_MAX_ENTRY_BYTES").unwrap_or(64 * 1024);
+
+        if cache_ttl == 0 {
+            source.error(format!(
+                "{} must be greater than 0",
+                source.label("CACHE_TTL")
+            ));
+        }
+
+        let mut cache = CacheConfig {
+            commands: vec![],
+            max_bytes: source.number("CACHE_MAX_BYTES").unwrap_or(64 * 1024 * 1024),
+        };
+
+        let http_cache = HttpCacheConfig {
+            max_age: source.optional("HTTP_CACHE_MAX_AGE", 0),
+            public: source.bool("HTTP_CACHE_PUBLIC").unwrap_or(false),
+        };
+
+        let cors = CorsConfig {
+            allowed_origins: source.list("CORS_ALLOWED_ORIGINS"),
+            allowed_methods: match source.string("CORS_ALLOWED_METHODS") {
+                Some(_) => source.list("CORS_ALLOWED_METHODS"),
+                None => ["GET", "POST", "PUT", "DELETE"]
+                    .iter()
+                    .map(|method| method.to_string())
+                    .collect(),
+            },
+            allowed_headers: match source.string("CORS_ALLOWED_HEADERS") {
+                Some(_) => source.list("CORS_ALLOWED_HEADERS"),
+                None => DEFAULT_CORS_HEADERS
+                    .iter()
+                    .map(|header| header.to_string())
+                    .collect(),
+            },
+            allow_credentials: source.bool("CORS_ALLOW_CREDENTIALS").unwrap_or(false),
+            max_age: source.number("CORS_MAX_AGE").unwrap_or(600),
+        };
+
+        for origin in &cors.allowed_origins {
+            if origin != "*" && HeaderValue::from_str(origin).is_err() {
+                source.error(format!(
+                    "{}: invalid origin {:?}",
+                    source.label("CORS_ALLOWED_ORIGINS"),
+                    origin
+                ));
+            }
+        }
+
+        for method in &cors.allowed_methods {
+            if method != "*" && Method::from_bytes(method.as_bytes()).is_err() {
+                source.error(format!(
+                    "{}: invalid method {:?}",
+                    source.label("CORS_ALLOWED_METHODS"),
+                    method
+                ));
+            }
+        }
+
+        for header in &cors.allowed_headers {
+            if header != "*" && HeaderName::from_bytes(header.as_bytes()).is_err() {
+                source.error(format!(
+                    "{}: invalid header {:?}",
+                    source.label("CORS_ALLOWED_HEADERS"),
+                    header
+                ));
+            }
+        }
+
+        if cors.allow_credentials {
+            for (name, wildcard) in [
+                ("CORS_ALLOWED_ORIGINS", cors.allows_any_origin()),
+                ("CORS_ALLOWED_METHODS", cors.allows_any_method()),
+                ("CORS_ALLOWED_HEADERS", cors.allows_any_header()),
+            ] {
+                if wildcard {
+                    source.error(format!(
+                        "{} cannot be set when {} is `*`",
+                        source.label("CORS_ALLOW_CREDENTIALS"),
+                        source.label(name)
+                    ));
+                }
+            }
+        }
+
+        for entry in source.list("CACHE_COMMANDS") {
+            match CachedCommandConfig::parse(&entry, cache_ttl, cache_max_entry_bytes) {
+                Ok(command) => cache.commands.push(command),
+                Err(error) => {
+                    source.error(format!("{}: {}", source.label("CACHE_COMMANDS"), error))
+                }
+            }
+        }
+
+        let jwt = JwtConfig {
+            secret: source.string("JWT_SECRET"),
+            public_key_path: source.string("JWT_PUBLIC_KEY"),
+            public_key_algorithm: source
+                .string("JWT_ALGORITHM")
+                .unwrap_or_else(|| "RS256".to_string()),
+            jwks_path: source.string("JWT_JWKS_FILE"),
+            audience: source.list("JWT_AUDIENCE"),
+        };
+
+        let tls = match (
+            source.string("TLS_CERT_FILE"),
+            source.string("TLS_KEY_FILE"),
+        ) {
+            (Some(cert_path), Some(key_path)) => {
+                let client_ca_path = source.string("TLS_CLIENT_CA_FILE");
+
+                Some(TlsConfig {
+                    cert_path,
+                    key_path,
+                    redirect_port: source.number("TLS_REDIRECT_PORT"),
+                    reload_interval: source.number("TLS_RELOAD_INTERVAL").unwrap_or(30),
+                    // with a CA bundle, client certificates are required unless disabled
+                    client_cert_required: client_ca_path.is_some()
+                        && source.bool("TLS_CLIENT_CERT_REQUIRED").unwrap_or(true),
+                    client_ca_path,
+                    client_identities_path: source.string("TLS_CLIENT_IDENTITIES_FILE"),
+                })
+            }
+            (None, None) => None,
+            _ => {
+                source.error(format!(
+                    "{} and {} must be set together",
+                    source.label("TLS_CERT_FILE"),
+                    source.label("TLS_KEY_FILE")
+                ));
                 None
             }
         };
 
-        let env = args.env;
+        if let Some(tls) = &tls {
+            if tls.client_identities_path.is_some() && tls.client_ca_path.is_none() {
+                source.error(format!(
+                    "{} requires {}",
+                    source.label("TLS_CLIENT_IDENTITIES_FILE"),
+                    source.label("TLS_CLIENT_CA_FILE")
+                ));
+            }
+        }
+
+        let logging = LoggingConfig {
+            level: match source.string("LOG_LEVEL") {
+                Some(level) => Level::from_str(&level).unwrap_or_else(|_| {
+                    source.error(format!(
+                        "{} must be one of trace, debug, info, warn or error",
+                        source.label("LOG_LEVEL")
+                    ));
+                    Level::INFO
+                }),
+                None => Level::INFO,
+            },
+            format: match source.string("LOG_FORMAT").as_deref() {
+                None | Some("compact") => LogFormat::Compact,
+                Some("json") => LogFormat::Json,
+                Some(_) => {
+                    source.error(format!(
+                        "{} must be compact or json",
+                        source.label("LOG_FORMAT")
+                    ));
+                    LogFormat::Compact
+                }
+            },
+        };
+
+        let client_identities_configured = tls
+            .as_ref()
+            .is_some_and(|tls| tls.client_identities_path.is_some());
+
+        if tokens.is_empty()
+            && tokens_file.is_none()
+            && !jwt.is_enabled()
+            && !client_identities_configured
+        {
+            eprintln!("Warning: Server is running without a token. Please set TOKEN variable in .env file to secure the server");
+        }
+
+        if !source.errors.is_empty() {
+            return Err(source.errors);
+        }
+
+        let env = args.env.clone();
 
-        AppConfig {
+        Ok(AppConfig {
             server_port,
+            server_bind,
+            unix_socket,
+            shutdown_grace_period,
             redis_url,
+            redis_username,
+            redis_password,
+            redis_tls,
+            redis_pool,
+            blocking_pool,
+            max_block_time,
+            scripts_dir,
+            functions_dir,
             token,
+            elevated_token,
+            tokens,
+            tokens_file,
+            tokens_reload_interval,
+            allow_query_token,
+            auth_max_failures,
+            auth_lockout_seconds,
+            dangerous_commands,
+            dangerous_command_policy,
+            rate_limit,
+            request_limits,
+            cache,
+            http_cache,
+            cors,
+            jwt,
+            tls,
+            logging,
             env,
+        })
+    }
+}
+
+/// Reads settings by environment variable name, from the command line first, the environment
+/// second and the configuration file last. Invalid values are collected so all of them are
+/// reported at once.
+struct ConfigSource {
+    overrides: Vec<(&'static str, &'static str, String)>,
+    file: ConfigFile,
+    errors: Vec<String>,
+}
+
+impl ConfigSource {
+    fn new(overrides: Vec<(&'static str, &'static str, String)>, file: ConfigFile) -> Self {
+        ConfigSource {
+            overrides,
+            file,
+            errors: vec![],
+        }
+    }
+
+    fn override_value(&self, name: &str) -> Option<&(&'static str, &'static str, String)> {
+        self.overrides
+            .iter()
+            .find(|(env_name, _, _)| *env_name == name)
+    }
+
+    fn error(&mut self, error: impl Into<String>) {
+        self.errors.push(error.into());
+    }
+
+    /// Names a setting the way the user set it, the file key when it comes from the file.
+    fn label(&self, name: &str) -> String {
+        if let Some((_, flag, _)) = self.override_value(name) {
+            return flag.to_string();
+        }
+
+        match (env::var(name).ok(), self.file.get(name)) {
+            (None, Some(value)) => value.key.clone(),
+            _ => name.to_string(),
+        }
+    }
+
+    /// Reads an optional setting, treating an empty value as unset.
+    fn string(&self, name: &str) -> Option<String> {
+        self.override_value(name)
+            .map(|(_, _, value)| value.clone())
+            .or_else(|| env::var(name).ok())
+            .or_else(|| self.file.get(name).map(|value| value.value.clone()))
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Reads a comma separated list, empty when unset.
+    fn list(&self, name: &str) -> Vec<String> {
+        self.string(name)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Reads an optional boolean, recording an error if it is set but invalid.
+    fn bool(&mut self, name: &str) -> Option<bool> {
+        let value = self.string(name)?;
+
+        match value.to_lowercase().as_str() {
+            "true" | "1" | "yes" => Some(true),
+            "false" | "0" | "no" => Some(false),
+            _ => {
+                self.error(format!("{} must be true or false", self.label(name)));
+                None
+            }
         }
     }
+
+    /// Reads an optional number, recording an error if it is set but invalid.
+    fn number<T: FromStr>(&mut self, name: &str) -> Option<T> {
+        self.parse(name, "a number")
+    }
+
+    /// Parses an optional setting, recording an error naming `expected` if it is invalid.
+    fn parse<T: FromStr>(&mut self, name: &str, expected: &str) -> Option<T> {
+        let value = self.string(name)?;
+
+        match value.parse::<T>() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                self.error(format!("{} must be {}", self.label(name), expected));
+                None
+            }
+        }
+    }
+
+    /// Reads a number with a default, where 0 disables the setting. The unit is given by
+    /// the setting, e.g. milliseconds for `*_MS` timeouts.
+    fn optional<T: FromStr + Default + PartialEq>(&mut self, name: &str, default: T) -> Option<T> {
+        Some(self.number(name).unwrap_or(default)).filter(|value| *value != T::default())
+    }
 }
@@ -0,0 +1,299 @@
+// src/config_file.rs
+
+use std::{collections::HashMap, fs, path::Path};
+
+use serde_json::Value;
+
+/// Keys accepted in the configuration file, as `(section, key, environment variable)`.
+/// Every file setting has an environment variable of the same meaning, which overrides it.
+pub const FILE_KEYS: &[(&str, &str, &str)] = &[
+    ("server", "port", "SERVER_PORT"),
+    ("server", "bind", "BIND_ADDRESS"),
+    ("server", "unix_socket", "UNIX_SOCKET"),
+    ("server", "unix_socket_mode", "UNIX_SOCKET_MODE"),
+    ("server", "shutdown_grace_period", "SHUTDOWN_GRACE_PERIOD"),
+    ("server", "http_cache_max_age", "HTTP_CACHE_MAX_AGE"),
+    ("server", "http_cache_public", "HTTP_CACHE_PUBLIC"),
+    ("server", "tls_cert_file", "TLS_CERT_FILE"),
+    ("server", "tls_key_file", "TLS_KEY_FILE"),
+    ("server", "tls_redirect_port", "TLS_REDIRECT_PORT"),
+    ("server", "tls_reload_interval", "TLS_RELOAD_INTERVAL"),
+    ("server", "tls_client_ca_file", "TLS_CLIENT_CA_FILE"),
+    (
+        "server",
+        "tls_client_cert_required",
+        "TLS_CLIENT_CERT_REQUIRED",
+    ),
+    (
+        "server",
+        "tls_client_identities_file",
+        "TLS_CLIENT_IDENTITIES_FILE",
+    ),
+    ("redis", "url", "REDIS_URL"),
+    ("redis", "username", "REDIS_USERNAME"),
+    ("redis", "password", "REDIS_PASSWORD"),
+    ("redis", "tls_ca_file", "REDIS_TLS_CA_FILE"),
+    ("redis", "tls_cert_file", "REDIS_TLS_CERT_FILE"),
+    ("redis", "tls_key_file", "REDIS_TLS_KEY_FILE"),
+    ("redis", "tls_insecure", "REDIS_TLS_INSECURE"),
+    ("redis", "pool_max_size", "REDIS_POOL_MAX_SIZE"),
+    (
+        "redis",
+        "pool_wait_timeout_ms",
+        "REDIS_POOL_WAIT_TIMEOUT_MS",
+    ),
+    (
+        "redis",
+        "pool_create_timeout_ms",
+        "REDIS_POOL_CREATE_TIMEOUT_MS",
+    ),
+    (
+        "redis",
+        "pool_recycle_timeout_ms",
+        "REDIS_POOL_RECYCLE_TIMEOUT_MS",
+    ),
+    (
+        "redis",
+        "connection_max_lifetime",
+        "REDIS_CONNECTION_MAX_LIFETIME",
+    ),
+    ("redis", "command_timeout_ms", "REDIS_COMMAND_TIMEOUT_MS"),
+    ("redis", "blocking_pool_max_size", "BLOCKING_POOL_MAX_SIZE"),
+    (
+        "redis",
+        "blocking_pool_wait_timeout_ms",
+        "BLOCKING_POOL_WAIT_TIMEOUT_MS",
+    ),
+    ("redis", "max_block_time_ms", "MAX_BLOCK_TIME_MS"),
+    ("redis", "scripts_dir", "SCRIPTS_DIR"),
+    ("redis", "functions_dir", "FUNCTIONS_DIR"),
+    ("auth", "token", "TOKEN"),
+    ("auth", "elevated_token", "ELEVATED_TOKEN"),
+    ("auth", "tokens", "TOKENS"),
+    ("auth", "tokens_file", "TOKENS_FILE"),
+    ("auth", "tokens_reload_interval", "TOKENS_RELOAD_INTERVAL"),
+    ("auth", "allow_query_token", "ALLOW_QUERY_TOKEN"),
+    ("auth", "max_failures", "AUTH_MAX_FAILURES"),
+    ("auth", "lockout_seconds", "AUTH_LOCKOUT_SECONDS"),
+    ("auth", "dangerous_commands", "DANGEROUS_COMMANDS"),
+    ("auth", "dangerous_scripting", "DANGEROUS_SCRIPTING"),
+    (
+        "auth",
+        "dangerous_command_policy",
+        "DANGEROUS_COMMAND_POLICY",
+    ),
+    ("auth", "jwt_secret", "JWT_SECRET"),
+    ("auth", "jwt_public_key", "JWT_PUBLIC_KEY"),
+    ("auth", "jwt_algorithm", "JWT_ALGORITHM"),
+    ("auth", "jwt_jwks_file", "JWT_JWKS_FILE"),
+    ("auth", "jwt_audience", "JWT_AUDIENCE"),
+    (
+        "limits",
+        "token_requests_per_second",
+        "TOKEN_REQUESTS_PER_SECOND",
+    ),
+    (
+        "limits",
+        "token_commands_per_second",
+        "TOKEN_COMMANDS_PER_SECOND",
+    ),
+    ("limits", "ip_requests_per_second", "IP_REQUESTS_PER_SECOND"),
+    ("limits", "ip_commands_per_second", "IP_COMMANDS_PER_SECOND"),
+    ("limits", "daily_command_quota", "DAILY_COMMAND_QUOTA"),
+    ("limits", "max_body_bytes", "MAX_BODY_BYTES"),
+    ("limits", "max_pipeline_commands", "MAX_PIPELINE_COMMANDS"),
+    ("limits", "max_command_arguments", "MAX_COMMAND_ARGUMENTS"),
+    ("limits", "max_reply_bytes", "MAX_REPLY_BYTES"),
+    ("cache", "commands", "CACHE_COMMANDS"),
+    ("cache", "ttl", "CACHE_TTL"),
+    ("cache", "max_entry_bytes", "CACHE_MAX_ENTRY_BYTES"),
+    ("cache", "max_bytes", "CACHE_MAX_BYTES"),
+    ("cors", "allowed_origins", "CORS_ALLOWED_ORIGINS"),
+    ("cors", "allowed_methods", "CORS_ALLOWED_METHODS"),
+    ("cors", "allowed_headers", "CORS_ALLOWED_HEADERS"),
+    ("cors", "allow_credentials", "CORS_ALLOW_CREDENTIALS"),
+    ("cors", "max_age", "CORS_MAX_AGE"),
+    ("logging", "level", "LOG_LEVEL"),
+    ("logging", "format", "LOG_FORMAT"),
+];
+
+/// A setting read from the configuration file.
+#[derive(Debug, Clone)]
+pub struct FileValue {
+    /// Location of the setting, e.g. `server.port`
+    pub key: String,
+    /// The value in the form of its environment variable, lists joined with commas
+    pub value: String,
+}
+
+/// Settings of a TOML or YAML configuration file, keyed by environment variable name.
+#[derive(Debug, Default)]
+pub struct ConfigFile {
+    values: HashMap<&'static str, FileValue>,
+}
+
+impl ConfigFile {
+    /// Reads the file, choosing the format from its extension.
+    pub fn load(path: &str) -> Result<Self, Vec<String>> {
+        let content =
+            fs::read_to_string(path).map_err(|e| vec![format!("cannot read {}: {}", path, e)])?;
+
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+
+        match extension {
+            "toml" => Self::parse_toml(&content),
+            "yaml" | "yml" => Self::parse_yaml(&content),
+            _ => Err(vec![
+                "configuration files must end in .toml, .yaml or .yml".to_string()
+            ]),
+        }
+        .map_err(|errors| {
+            errors
+                .into_iter()
+                .map(|error| format!("{}: {}", path, error))
+                .collect()
+        })
+    }
+
+    pub fn parse_toml(content: &str) -> Result<Self, Vec<String>> {
+        let document: toml::Value = toml::from_str(content).map_err(|e| vec![e.to_string()])?;
+
+        Self::from_document(serde_json::to_value(document).map_err(|e| vec![e.to_string()])?)
+    }
+
+    pub fn parse_yaml(content: &str) -> Result<Self, Vec<String>> {
+        let document: serde_yaml::Value =
+            serde_yaml::from_str(content).map_err(|e| vec![e.to_string()])?;
+
+        Self::from_document(serde_json::to_value(document).map_err(|e| vec![e.to_string()])?)
+    }
+
+    fn from_document(document: Value) -> Result<Self, Vec<String>> {
+        let mut values = HashMap::new();
+        let mut errors = vec![];
+
+        let sections = match document {
+            Value::Object(sections) => sections,
+            Value::Null => return Ok(ConfigFile::default()),
+            _ => return Err(vec!["expected a table of sections".to_string()]),
+        };
+
+        for (section, settings) in sections {
+            if !FILE_KEYS.iter().any(|(known, _, _)| *known == section) {
+                errors.push(format!("unknown section [{}]", section));
+                continue;
+            }
+
+            let Value::Object(settings) = settings else {
+                errors.push(format!("[{}] must be a table", section));
+                continue;
+            };
+
+            for (key, value) in settings {
+                let path = format!("{}.{}", section, key);
+
+                let Some((_, _, name)) = FILE_KEYS.iter().find(|(known_section, known_key, _)| {
+                    *known_section == section && *known_key == key
+                }) else {
+                    errors.push(format!("unknown setting {}", path));
+                    continue;
+                };
+
+                match flatten(&value) {
+                    Some(value) => {
+                        values.insert(*name, FileValue { key: path, value });
+                    }
+                    None => errors.push(format!(
+                        "{} must be a string, number, boolean or list of those",
+                        path
+                    )),
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(ConfigFile { values })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Returns the file setting for an environment variable name.
+    pub fn get(&self, name: &str) -> Option<&FileValue> {
+        self.values.get(name)
+    }
+}
+
+/// Converts a file value to the string its environment variable would hold.
+fn flatten(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        Value::Bool(value) => Some(value.to_string()),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::Array(_) => None,
+                item => flatten(item),
+            })
+            .collect::<Option<Vec<String>>>()
+            .map(|items| items.join(",")),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConfigFile;
+
+    #[test]
+    fn test_toml_and_yaml() {
+        let toml = ConfigFile::parse_toml(
+            r#"
+            [server]
+            port = 8080
+
+            [auth]
+            tokens = ["ci:first", "deploy:second"]
+            allow_query_token = false
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(toml.get("SERVER_PORT").unwrap().value, "8080");
+        assert_eq!(toml.get("SERVER_PORT").unwrap().key, "server.port");
+        assert_eq!(toml.get("TOKENS").unwrap().value, "ci:first,deploy:second");
+        assert_eq!(toml.get("ALLOW_QUERY_TOKEN").unwrap().value, "false");
+
+        let yaml = ConfigFile::parse_yaml(
+            "redis:\n  url: redis://127.0.0.1:6379\nlimits:\n  daily_command_quota: 1000\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            yaml.get("REDIS_URL").unwrap().value,
+            "redis://127.0.0.1:6379"
+        );
+        assert_eq!(yaml.get("DAILY_COMMAND_QUOTA").unwrap().value, "1000");
+    }
+
+    #[test]
+    fn test_unknown_settings() {
+        let errors = ConfigFile::parse_toml(
+            r#"
+            [server]
+            prot = 8080
+
+            [metrics]
+            port = 1
+            "#,
+        )
+        .unwrap_err();
+
+        assert!(errors.contains(&"unknown setting server.prot".to_string()));
+        assert!(errors.contains(&"unknown section [metrics]".to_string()));
+    }
+}
@@ -1,9 +1,15 @@
 pub mod cmd;
+pub mod commands;
 pub mod config;
+pub mod config_file;
 pub mod middleware;
 pub mod models;
 pub mod routes;
 pub mod services;
+pub mod shutdown;
 pub mod state;
+pub mod tls;
+#[cfg(unix)]
+pub mod unix_socket;
 pub mod utils;
 pub mod web;
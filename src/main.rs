@@ -1,15 +1,39 @@
 use clap::{CommandFactory, Parser};
-use rediserve::cmd::Args;
+use rediserve::cmd::{Args, Commands, ConfigCommands, TokenCommands};
+use rediserve::commands::{check, cli, token};
 use rediserve::web::start_server;
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
 
-    if args.start {
-        start_server(args).await;
-    } else {
-        Args::command().print_help().unwrap();
-        std::process::exit(1);
+    match &args.command {
+        Some(Commands::Serve(_)) => start_server(args).await,
+        Some(Commands::Check(_)) => {
+            if !check::check(&args).await {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Token {
+            command: TokenCommands::Generate { bytes },
+        }) => println!("{}", token::generate(*bytes)),
+        Some(Commands::Config {
+            command: ConfigCommands::Check,
+        }) => {
+            if check::check_config(&args).is_none() {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Cli(cli_args)) => {
+            if !cli::run(cli_args).await {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Version) => println!("rediserve {}", env!("CARGO_PKG_VERSION")),
+        None if args.start => start_server(args).await,
+        None => {
+            Args::command().print_help().unwrap();
+            std::process::exit(1);
+        }
     }
 }
@@ -1,53 +1,161 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
 use axum::{
     body::Body,
-    extract::Request,
+    extract::{ConnectInfo, Request},
     http::{header, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
     Extension,
 };
 
-use crate::state::AppState;
+use crate::{
+    models::{AuthContext, ClientCertificate},
+    state::AppState,
+};
 
 pub async fn check_auth(
     Extension(app_state): Extension<Arc<AppState>>,
-    req: Request<Body>,
+    mut req: Request<Body>,
     next: Next,
 ) -> Result<Response<Body>, StatusCode> {
-    let user_token = match app_state.token.as_ref() {
-        Some(token) => token,
-        None => return Ok(next.run(req).await),
-    };
-
     let auth_header = req
         .headers()
         .get(header::AUTHORIZATION)
         .and_then(|header| header.to_str().ok())
         .filter(|header| header.starts_with("Bearer "))
-        .map(|header| header.trim_start_matches("Bearer "));
-
-    if let Some(auth_token) = auth_header {
-        if auth_token == user_token {
-            return Ok(next.run(req).await);
-        }
-    }
+        .map(|header| header.trim_start_matches("Bearer ").to_string());
 
     let query_params = req.uri().query().unwrap_or("");
     let query_token = url::form_urlencoded::parse(query_params.as_bytes())
         .find(|(key, _)| key == "_token")
-        .map(|(_, value)| value.into_owned());
+        .map(|(_, value)| value.into_owned())
+        .filter(|_| app_state.allow_query_token);
+
+    let provided_tokens: Vec<String> = auth_header.into_iter().chain(query_token).collect();
+
+    let client_ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    if let Some(ip) = client_ip {
+        if let Err(error) = app_state.auth_lockout.check(ip) {
+            return Ok(error.into_response());
+        }
+    }
+
+    // set by the TLS acceptor when the client presented a verified certificate
+    let client_cert = req
+        .extensions()
+        .get::<Option<ClientCertificate>>()
+        .and_then(Option::as_ref);
+
+    let mut auth = match authenticate(&app_state, &provided_tokens, client_cert) {
+        Some(auth) => auth,
+        None => {
+            tracing::warn!(uri = %req.uri().path(), "rejected request with invalid token");
+            if let Some(ip) = client_ip {
+                app_state.auth_lockout.record_failure(ip);
+            }
+            return Ok(unauthorized());
+        }
+    };
+
+    tracing::info!(token = %auth.name, "authenticated request");
+
+    auth.client_ip = client_ip;
+
+    req.extensions_mut().insert(auth);
+
+    Ok(next.run(req).await)
+}
 
-    if query_token == Some(user_token.to_string()) {
-        return Ok(next.run(req).await);
+/// Resolves the caller from the provided tokens, trying the static tokens before JWTs
+/// and the client certificate last.
+/// Without any configured token, JWT key or certificate identity every request is accepted anonymously.
+fn authenticate(
+    app_state: &AppState,
+    provided_tokens: &[String],
+    client_cert: Option<&ClientCertificate>,
+) -> Option<AuthContext> {
+    if let Some(auth) = app_state.tokens.authenticate(provided_tokens) {
+        return Some(auth);
     }
 
-    return Ok(Response::builder()
+    if let Some(verifier) = &app_state.jwt_verifier {
+        let auth = provided_tokens
+            .iter()
+            .find_map(|provided| verifier.verify(provided));
+        if auth.is_some() {
+            return auth;
+        }
+    }
+
+    if let (Some(certificate_auth), Some(client_cert)) = (&app_state.certificate_auth, client_cert)
+    {
+        if let Some(auth) = certificate_auth.authenticate(client_cert) {
+            return Some(auth);
+        }
+    }
+
+    if app_state.tokens.is_configured()
+        || app_state.jwt_verifier.is_some()
+        || app_state.certificate_auth.is_some()
+    {
+        None
+    } else {
+        Some(AuthContext::default())
+    }
+}
+
+fn unauthorized() -> Response<Body> {
+    Response::builder()
         .status(StatusCode::UNAUTHORIZED)
         .header(header::CONTENT_TYPE, "application/json")
         .body(Body::from(
             serde_json::json!({"error": "Unauthorized access"}).to_string(),
         ))
-        .unwrap());
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{Ipv4Addr, SocketAddr},
+        sync::Arc,
+        time::Duration,
+    };
+
+    use axum::{extract::ConnectInfo, http::StatusCode, Extension};
+    use axum_test::TestServer;
+    use clap::Parser;
+
+    use crate::cmd::Args;
+    use crate::routes::redis_route::redis_routes;
+    use crate::services::AuthLockout;
+    use crate::utils::app_setup::{add_layers, app_setup};
+
+    #[tokio::test]
+    async fn test_success_does_not_reset_lockout() {
+        let (config, app_state) = app_setup(Args::parse());
+        let token = config.token.unwrap();
+
+        let mut app_state = (*app_state).clone();
+        app_state.auth_lockout = Arc::new(AuthLockout::new(2, Duration::from_secs(60)));
+
+        let addr = SocketAddr::from((Ipv4Addr::new(10, 0, 0, 1), 4000));
+        let app =
+            add_layers(redis_routes(), Arc::new(app_state)).layer(Extension(ConnectInfo(addr)));
+        let server = TestServer::new(app).unwrap();
+
+        let get = |token: &str| server.get("/get/lockout").add_query_param("_token", token);
+
+        get("wrong").await.assert_status(StatusCode::UNAUTHORIZED);
+        get(&token).await.assert_status(StatusCode::OK);
+        get("wrong").await.assert_status(StatusCode::UNAUTHORIZED);
+        get(&token)
+            .await
+            .assert_status(StatusCode::TOO_MANY_REQUESTS);
+    }
 }
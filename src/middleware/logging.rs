@@ -1,10 +1,77 @@
-use tower_http::trace::{self, TraceLayer};
-use tracing::Level;
+use axum::http::{Request, Uri};
+use tower_http::trace::{self, MakeSpan, TraceLayer};
+use tracing::{Level, Span};
 
-pub fn get_trace_layer(
-) -> TraceLayer<tower_http::classify::SharedClassifier<tower_http::classify::ServerErrorsAsFailures>>
-{
+/// Query parameters whose values never appear in logs.
+const REDACTED_PARAMS: [&str; 1] = ["_token"];
+
+/// Creates request spans like `DefaultMakeSpan`, with tokens redacted from the URI.
+#[derive(Debug, Clone, Copy)]
+pub struct RedactedMakeSpan;
+
+impl<B> MakeSpan<B> for RedactedMakeSpan {
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        tracing::info_span!(
+            "request",
+            method = %request.method(),
+            uri = %redact_uri(request.uri()),
+            version = ?request.version(),
+        )
+    }
+}
+
+/// Returns the URI with the values of `REDACTED_PARAMS` replaced. Keys are decoded the same
+/// way `check_auth` decodes them, so an encoded key such as `%5Ftoken` is redacted too.
+pub fn redact_uri(uri: &Uri) -> String {
+    let Some(query) = uri.query() else {
+        return uri.to_string();
+    };
+
+    let query = query
+        .split('&')
+        .map(|pair| {
+            let key = pair.split('=').next().unwrap_or_default();
+            let decoded = url::form_urlencoded::parse(key.as_bytes())
+                .next()
+                .map(|(decoded, _)| decoded)
+                .unwrap_or_default();
+
+            if REDACTED_PARAMS.contains(&decoded.as_ref()) {
+                format!("{}=[REDACTED]", key)
+            } else {
+                pair.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("&");
+
+    format!("{}?{}", uri.path(), query)
+}
+
+pub fn get_trace_layer() -> TraceLayer<
+    tower_http::classify::SharedClassifier<tower_http::classify::ServerErrorsAsFailures>,
+    RedactedMakeSpan,
+> {
     TraceLayer::new_for_http()
-        .make_span_with(trace::DefaultMakeSpan::new().level(Level::INFO))
+        .make_span_with(RedactedMakeSpan)
         .on_response(trace::DefaultOnResponse::new().level(Level::INFO))
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Uri;
+
+    use super::redact_uri;
+
+    #[test]
+    fn test_redact_uri() {
+        let uri: Uri = "/get/key?_token=secret&count=1".parse().unwrap();
+        assert_eq!(redact_uri(&uri), "/get/key?_token=[REDACTED]&count=1");
+
+        let uri: Uri = "/get/key?count=1&%5Ftoken=secret".parse().unwrap();
+        assert_eq!(redact_uri(&uri), "/get/key?count=1&%5Ftoken=[REDACTED]");
+
+        let uri: Uri = "/get/key".parse().unwrap();
+        assert_eq!(redact_uri(&uri), "/get/key");
+    }
+}
@@ -1,4 +1,6 @@
 pub mod auth_check;
 pub mod logging;
+pub mod rate_limit;
 pub use auth_check::check_auth;
 pub use logging::get_trace_layer;
+pub use rate_limit::rate_limit;
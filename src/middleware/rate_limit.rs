@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension,
+};
+
+use crate::{models::AuthContext, state::AppState};
+
+/// Applies the per token and per IP request limits, commands are counted by the routes.
+pub async fn rate_limit(
+    Extension(app_state): Extension<Arc<AppState>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response<Body> {
+    let auth = req
+        .extensions()
+        .get::<AuthContext>()
+        .cloned()
+        .unwrap_or_default();
+
+    if let Err(error) = app_state.rate_limiter.check_request(&auth) {
+        return error.into_response();
+    }
+
+    next.run(req).await
+}
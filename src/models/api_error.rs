@@ -1,12 +1,120 @@
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use deadpool_redis::PoolError;
 use redis::RedisError;
 use thiserror::Error;
 
+use super::response_builder::ResponseBuilder;
+
 #[derive(Error, Debug)]
 pub enum ApiError {
     #[error("Redis error: {0}")]
     RedisError(#[from] RedisError),
+    #[error("Connection pool error: {0}")]
+    PoolError(#[from] PoolError),
     #[error("invalid token")]
     InvalidToken,
     #[error("No Command")]
     NoCommand,
+    #[error("Command not allowed: {0}")]
+    CommandNotAllowed(String),
+    #[error("Key not allowed: {0}")]
+    KeyNotAllowed(String),
+    #[error("Rate limit exceeded")]
+    RateLimited { retry_after: u64 },
+    #[error("Daily command quota exceeded")]
+    QuotaExceeded { retry_after: u64 },
+    #[error("Too many failed authentication attempts")]
+    LockedOut { retry_after: u64 },
+    #[error("Command timed out")]
+    Timeout,
+    #[error("Unknown script: {0}")]
+    UnknownScript(String),
+    #[error("Unknown command: {0}")]
+    UnknownCommand(String),
+    #[error("Invalid script: {0}")]
+    InvalidScript(String),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    #[error("Request too large: {0}")]
+    RequestTooLarge(String),
+    #[error("Reply too large: {size} bytes, the limit is {limit}")]
+    ReplyTooLarge { size: usize, limit: usize },
+}
+
+impl ApiError {
+    /// HTTP status code used when the error rejects the whole request.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::InvalidToken => StatusCode::UNAUTHORIZED,
+            ApiError::PoolError(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::CommandNotAllowed(_) | ApiError::KeyNotAllowed(_) => StatusCode::FORBIDDEN,
+            // the Redis ACL user of the caller lacks permission for the command or key
+            ApiError::RedisError(error) if error.code() == Some("NOPERM") => StatusCode::FORBIDDEN,
+            ApiError::RateLimited { .. }
+            | ApiError::QuotaExceeded { .. }
+            | ApiError::LockedOut { .. } => StatusCode::TOO_MANY_REQUESTS,
+            ApiError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            ApiError::UnknownScript(_) | ApiError::UnknownCommand(_) => StatusCode::NOT_FOUND,
+            ApiError::InvalidScript(_) | ApiError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            ApiError::RequestTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::ReplyTooLarge { .. } => StatusCode::INSUFFICIENT_STORAGE,
+            _ => StatusCode::OK,
+        }
+    }
+
+    /// Seconds the client should wait before retrying, sent as the `Retry-After` header.
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            ApiError::RateLimited { retry_after }
+            | ApiError::QuotaExceeded { retry_after }
+            | ApiError::LockedOut { retry_after } => Some(*retry_after),
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+
+        match self.retry_after() {
+            Some(retry_after) => (
+                status,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                Json(ResponseBuilder::error(self)),
+            )
+                .into_response(),
+            None => (status, Json(ResponseBuilder::error(self))).into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use redis::{ErrorKind, RedisError};
+
+    use super::ApiError;
+
+    #[test]
+    fn test_noperm_is_forbidden() {
+        let noperm = redis::parse_redis_value(
+            b"-NOPERM this user has no permissions to run the 'flushall' command\r\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            ApiError::RedisError(noperm).status_code(),
+            StatusCode::FORBIDDEN
+        );
+
+        let wrong_type = RedisError::from((ErrorKind::TypeError, "WRONGTYPE"));
+        assert_eq!(
+            ApiError::RedisError(wrong_type).status_code(),
+            StatusCode::OK
+        );
+    }
 }
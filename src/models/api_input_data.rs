@@ -1,14 +1,14 @@
 use axum::{
     async_trait,
     body::{Body, Bytes},
-    extract::{FromRequest, FromRequestParts, Request},
-    http::{header::HeaderName, request::Parts, HeaderValue},
-    response::Response,
+    extract::{rejection::BytesRejection, FromRequest, FromRequestParts, Request},
+    http::{header::HeaderName, request::Parts, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
 };
 
 use serde::Deserialize;
 
-use super::api_types::JsonValue;
+use super::{api_types::JsonValue, ApiError, Argument};
 
 #[derive(Deserialize, Debug)]
 pub enum ApiInputValue {
@@ -19,10 +19,23 @@ pub enum ApiInputValue {
 
 pub struct ApiInput(pub ApiInputValue);
 
+/// Rejects a body that cannot be read, with 413 when it is above `MAX_BODY_BYTES`.
+pub fn body_rejection(rejection: BytesRejection) -> Response {
+    if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        return ApiError::RequestTooLarge("the body is above the size limit".to_string())
+            .into_response();
+    }
+
+    Response::builder()
+        .status(400)
+        .body(Body::from("invalid body"))
+        .unwrap()
+}
+
 #[async_trait]
 impl<S> FromRequest<S> for ApiInput
 where
-    Bytes: FromRequest<S>,
+    Bytes: FromRequest<S, Rejection = BytesRejection>,
     S: Send + Sync,
 {
     type Rejection = Response;
@@ -37,12 +50,9 @@ where
             }
             Some(content_type) => {
                 if content_type == HeaderValue::from_static("application/json") {
-                    let body = Bytes::from_request(req, state).await.map_err(|_| {
-                        Response::builder()
-                            .status(400)
-                            .body(Body::from("invalid body"))
-                            .unwrap()
-                    })?;
+                    let body = Bytes::from_request(req, state)
+                        .await
+                        .map_err(body_rejection)?;
 
                     // parse json from bytes
 
@@ -57,12 +67,9 @@ where
 
                     return Ok(deserialized);
                 } else {
-                    let body = Bytes::from_request(req, state).await.map_err(|_| {
-                        Response::builder()
-                            .status(400)
-                            .body(Body::from("invalid body"))
-                            .unwrap()
-                    })?;
+                    let body = Bytes::from_request(req, state)
+                        .await
+                        .map_err(body_rejection)?;
 
                     let deserialized: ApiInput = String::from_utf8(body.to_vec())
                         .map_err(|_| {
@@ -132,3 +139,22 @@ where
         }
     }
 }
+
+/// Keys and arguments of a script or function call, kept apart so the key count is never wrong.
+#[derive(Deserialize, Debug, Default)]
+pub struct CallInput {
+    #[serde(default)]
+    pub keys: Vec<JsonValue>,
+    #[serde(default)]
+    pub args: Vec<JsonValue>,
+}
+
+impl CallInput {
+    /// Arguments following the script or function name: `numkeys key [key ...] arg [arg ...]`.
+    pub fn into_arguments(self) -> Vec<Argument> {
+        let mut arguments = vec![Argument(JsonValue::Number(self.keys.len().into()))];
+        arguments.extend(self.keys.iter().map(Argument::from));
+        arguments.extend(self.args.iter().map(Argument::from));
+        arguments
+    }
+}
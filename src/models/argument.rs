@@ -6,6 +6,19 @@ use super::api_types::JsonValue;
 #[derive(Debug, Deserialize)]
 pub struct Argument(pub JsonValue);
 
+impl Argument {
+    /// Returns the argument as the string sent to Redis.
+    pub fn as_redis_string(&self) -> String {
+        match &self.0 {
+            JsonValue::String(string) => string.to_string(),
+            JsonValue::Number(number) => number.to_string(),
+            JsonValue::Bool(true) => "1".to_string(),
+            JsonValue::Bool(false) => "0".to_string(),
+            _ => "".to_string(),
+        }
+    }
+}
+
 impl From<&JsonValue> for Argument {
     fn from(arg: &JsonValue) -> Self {
         match arg {
@@ -0,0 +1,59 @@
+use std::{fmt, net::IpAddr};
+
+/// Identity of the caller, attached to every request by the `check_auth` middleware.
+#[derive(Debug, Clone)]
+pub struct AuthContext {
+    /// Name of the token used by the caller, used to key limits and quotas
+    pub name: String,
+    /// Whether the caller authenticated with the elevated token
+    pub elevated: bool,
+    /// Address of the client, if known
+    pub client_ip: Option<IpAddr>,
+    /// Commands the caller may run, all commands when unset
+    pub allowed_commands: Option<Vec<String>>,
+    /// Prefix every key used by the caller must start with
+    pub key_prefix: Option<String>,
+    /// Whether the caller is limited to commands that do not write
+    pub read_only: bool,
+    /// Redis ACL user the caller's commands run as, the default connection user when unset
+    pub redis_user: Option<RedisUser>,
+}
+
+/// Credentials of a Redis ACL user.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct RedisUser {
+    pub username: String,
+    pub password: Option<String>,
+}
+
+impl fmt::Debug for RedisUser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedisUser")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AuthContext {
+    /// Creates an unrestricted context for the given token name.
+    pub fn new(name: &str) -> Self {
+        AuthContext {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for AuthContext {
+    fn default() -> Self {
+        AuthContext {
+            name: "anonymous".to_string(),
+            elevated: false,
+            client_ip: None,
+            allowed_commands: None,
+            key_prefix: None,
+            read_only: false,
+            redis_user: None,
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
+
+/// Identity read from the verified certificate a client presented during the TLS handshake.
+#[derive(Debug, Clone, Default)]
+pub struct ClientCertificate {
+    /// Full subject, e.g. `CN=orders, O=mesh`
+    pub subject: String,
+    /// Common name of the subject
+    pub common_name: Option<String>,
+    /// DNS, URI, email and IP subject alternative names
+    pub alt_names: Vec<String>,
+}
+
+impl ClientCertificate {
+    /// Parses a DER encoded certificate, `None` if it cannot be read.
+    pub fn from_der(der: &[u8]) -> Option<Self> {
+        let (_, certificate) = X509Certificate::from_der(der).ok()?;
+
+        let common_name = certificate
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|name| name.as_str().ok())
+            .map(|name| name.to_string());
+
+        let alt_names = match certificate.subject_alternative_name() {
+            Ok(Some(extension)) => extension
+                .value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name)
+                    | GeneralName::URI(name)
+                    | GeneralName::RFC822Name(name) => Some(name.to_string()),
+                    GeneralName::IPAddress(bytes) => ip_address(bytes),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        Some(ClientCertificate {
+            subject: certificate.subject().to_string(),
+            common_name,
+            alt_names,
+        })
+    }
+
+    /// Every identity of the certificate: common name, full subject and alternative names.
+    pub fn identities(&self) -> impl Iterator<Item = &str> {
+        self.common_name
+            .iter()
+            .chain(std::iter::once(&self.subject))
+            .chain(self.alt_names.iter())
+            .map(|identity| identity.as_str())
+    }
+}
+
+fn ip_address(bytes: &[u8]) -> Option<String> {
+    match bytes.len() {
+        4 => Some(std::net::Ipv4Addr::from(<[u8; 4]>::try_from(bytes).ok()?).to_string()),
+        16 => Some(std::net::Ipv6Addr::from(<[u8; 16]>::try_from(bytes).ok()?).to_string()),
+        _ => None,
+    }
+}
@@ -1,6 +1,6 @@
 use serde::Deserialize;
 
-use super::Argument;
+use super::{api_types::JsonValue, Argument};
 
 #[derive(Debug, Deserialize)]
 pub struct Command {
@@ -8,6 +8,20 @@ pub struct Command {
     pub args: Vec<Argument>,
 }
 
+impl Command {
+    /// Builds a command from string arguments, keeping them as strings so values such as
+    /// `true` or `1` are sent unchanged.
+    pub fn from_strings(name: &str, args: impl IntoIterator<Item = String>) -> Self {
+        Command {
+            name: name.to_string(),
+            args: args
+                .into_iter()
+                .map(|arg| Argument(JsonValue::String(arg)))
+                .collect(),
+        }
+    }
+}
+
 impl AsRef<str> for Command {
     fn as_ref(&self) -> &str {
         &self.name
@@ -0,0 +1,499 @@
+use std::borrow::Cow;
+
+use serde::Serialize;
+
+use super::{api_types::RedisValue, Command};
+
+/// Where the keys of a command are found in its arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum KeySpec {
+    /// The command takes no keys
+    Keyless,
+    /// Keys at fixed positions, using the `COMMAND INFO` convention: positions are 1-based
+    /// and a negative last key counts from the end of the arguments.
+    Range { first: i64, last: i64, step: i64 },
+    /// A `numkeys` argument at the given argument index followed by that many keys,
+    /// optionally preceded by a destination key as the first argument.
+    NumKeys { index: usize, destination: bool },
+    /// Keys follow the `STREAMS` token, one key per stream id (XREAD, XREADGROUP).
+    Streams,
+    /// Keys whose positions depend on the arguments in a way the table does not describe,
+    /// as for movable-key commands reported by Redis that have no built-in entry and for
+    /// keys given as options (SORT, GEORADIUS).
+    Unknown,
+}
+
+/// Metadata about a Redis command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandSpec {
+    /// Upper case name, subcommands as `CONTAINER|SUBCOMMAND` (e.g. `OBJECT|ENCODING`)
+    pub name: Cow<'static, str>,
+    pub arity: i64,
+    pub write: bool,
+    /// The command may block the connection, e.g. BLPOP or XREAD with BLOCK
+    pub blocking: bool,
+    pub keys: KeySpec,
+    /// Flags reported by Redis, e.g. `readonly` or `denyoom`
+    pub flags: Vec<String>,
+    /// ACL categories reported by Redis, e.g. `@read`
+    pub acl_categories: Vec<String>,
+}
+
+impl CommandSpec {
+    /// Parses an entry of a `COMMAND` or `COMMAND INFO` reply:
+    /// `[name, arity, flags, first key, last key, step, ...]`.
+    /// Redis does not describe the positions of movable keys in these fields, so they are
+    /// taken from the built-in table, and left unknown for commands missing from it.
+    /// A command marked as writing in the built-in table stays so, e.g. admin commands.
+    pub fn from_command_info(info: &RedisValue) -> Option<CommandSpec> {
+        let RedisValue::Bulk(fields) = info else {
+            return None;
+        };
+
+        let name = text(fields.first()?)?.to_uppercase();
+        let int = |index: usize| match fields.get(index) {
+            Some(RedisValue::Int(value)) => Some(*value),
+            _ => None,
+        };
+
+        let flags: Vec<String> = match fields.get(2)? {
+            RedisValue::Bulk(flags) => flags.iter().filter_map(text).collect(),
+            _ => return None,
+        };
+        // added in Redis 6
+        let acl_categories = match fields.get(6) {
+            Some(RedisValue::Bulk(categories)) => categories.iter().filter_map(text).collect(),
+            _ => vec![],
+        };
+        let has_flag = |flag: &str| flags.iter().any(|f| f.eq_ignore_ascii_case(flag));
+
+        // subcommands fall back to the entry of their container command
+        let builtin = lookup(&name).or_else(|| lookup(name.split('|').next()?));
+
+        let (first, last, step) = (int(3)?, int(4)?, int(5)?);
+        let keys = if has_flag("movablekeys") {
+            builtin.map(|spec| spec.keys).unwrap_or(KeySpec::Unknown)
+        } else if first == 0 {
+            KeySpec::Keyless
+        } else {
+            KeySpec::Range { first, last, step }
+        };
+
+        Some(CommandSpec {
+            name: Cow::Owned(name),
+            arity: int(1)?,
+            write: has_flag("write") || builtin.is_some_and(|spec| spec.write),
+            blocking: has_flag("blocking"),
+            keys,
+            flags,
+            acl_categories,
+        })
+    }
+
+    /// Returns false if the key positions of the command are not known.
+    pub fn keys_known(&self) -> bool {
+        self.keys != KeySpec::Unknown
+    }
+
+    /// Returns the indices in `command.args` holding keys.
+    pub fn key_indices(&self, command: &Command) -> Vec<usize> {
+        let len = command.args.len() as i64;
+
+        match self.keys {
+            KeySpec::Keyless | KeySpec::Unknown => vec![],
+            KeySpec::Range { first, last, step } => {
+                let last = if last < 0 { len + 1 + last } else { last };
+                let step = step.max(1) as usize;
+
+                (first..=last.min(len))
+                    .step_by(step)
+                    .filter(|position| *position >= 1)
+                    .map(|position| (position - 1) as usize)
+                    .collect()
+            }
+            KeySpec::NumKeys { index, destination } => {
+                let numkeys = command
+                    .args
+                    .get(index)
+                    .and_then(|arg| arg.as_redis_string().parse::<usize>().ok())
+                    .unwrap_or(0);
+
+                let first = index + 1;
+                let last = (first + numkeys).min(command.args.len());
+
+                let mut indices: Vec<usize> = if destination && !command.args.is_empty() {
+                    vec![0]
+                } else {
+                    vec![]
+                };
+                indices.extend(first..last);
+                indices
+            }
+            KeySpec::Streams => {
+                let streams = command
+                    .args
+                    .iter()
+                    .position(|arg| arg.as_redis_string().eq_ignore_ascii_case("STREAMS"));
+
+                match streams {
+                    Some(position) => {
+                        let first = position + 1;
+                        let count = (command.args.len() - first) / 2;
+                        (first..first + count).collect()
+                    }
+                    None => vec![],
+                }
+            }
+        }
+    }
+}
+
+/// Looks up a command in the built-in table, ignoring case.
+pub fn lookup(name: &str) -> Option<&'static CommandSpec> {
+    BUILTIN_COMMANDS
+        .iter()
+        .find(|spec| spec.name.eq_ignore_ascii_case(name))
+}
+
+fn text(value: &RedisValue) -> Option<String> {
+    match value {
+        RedisValue::Data(data) => String::from_utf8(data.clone()).ok(),
+        RedisValue::Status(status) => Some(status.clone()),
+        _ => None,
+    }
+}
+
+const fn spec(name: &'static str, arity: i64, write: bool, keys: KeySpec) -> CommandSpec {
+    CommandSpec {
+        name: Cow::Borrowed(name),
+        arity,
+        write,
+        blocking: false,
+        keys,
+        flags: Vec::new(),
+        acl_categories: Vec::new(),
+    }
+}
+
+const fn blocking_spec(name: &'static str, arity: i64, write: bool, keys: KeySpec) -> CommandSpec {
+    CommandSpec {
+        name: Cow::Borrowed(name),
+        arity,
+        write,
+        blocking: true,
+        keys,
+        flags: Vec::new(),
+        acl_categories: Vec::new(),
+    }
+}
+
+const NONE: KeySpec = KeySpec::Keyless;
+const ONE: KeySpec = KeySpec::Range {
+    first: 1,
+    last: 1,
+    step: 1,
+};
+const TWO: KeySpec = KeySpec::Range {
+    first: 1,
+    last: 2,
+    step: 1,
+};
+const ALL: KeySpec = KeySpec::Range {
+    first: 1,
+    last: -1,
+    step: 1,
+};
+const ALL_BUT_LAST: KeySpec = KeySpec::Range {
+    first: 1,
+    last: -2,
+    step: 1,
+};
+const PAIRS: KeySpec = KeySpec::Range {
+    first: 1,
+    last: -1,
+    step: 2,
+};
+const SECOND: KeySpec = KeySpec::Range {
+    first: 2,
+    last: 2,
+    step: 1,
+};
+const FROM_SECOND: KeySpec = KeySpec::Range {
+    first: 2,
+    last: -1,
+    step: 1,
+};
+const NUMKEYS_FIRST: KeySpec = KeySpec::NumKeys {
+    index: 0,
+    destination: false,
+};
+const NUMKEYS_SECOND: KeySpec = KeySpec::NumKeys {
+    index: 1,
+    destination: false,
+};
+const NUMKEYS_STORE: KeySpec = KeySpec::NumKeys {
+    index: 1,
+    destination: true,
+};
+/// Keys named by options anywhere in the arguments, such as `STORE` destinations
+const OPTIONS: KeySpec = KeySpec::Unknown;
+
+/// Built-in command table, used when the metadata cannot be fetched from Redis.
+pub static BUILTIN_COMMANDS: &[CommandSpec] = &[
+    // strings
+    spec("APPEND", 3, true, ONE),
+    spec("DECR", 2, true, ONE),
+    spec("DECRBY", 3, true, ONE),
+    spec("GET", 2, false, ONE),
+    spec("GETDEL", 2, true, ONE),
+    spec("GETEX", -2, true, ONE),
+    spec("GETRANGE", 4, false, ONE),
+    spec("GETSET", 3, true, ONE),
+    spec("INCR", 2, true, ONE),
+    spec("INCRBY", 3, true, ONE),
+    spec("INCRBYFLOAT", 3, true, ONE),
+    spec("LCS", -3, false, TWO),
+    spec("MGET", -2, false, ALL),
+    spec("MSET", -3, true, PAIRS),
+    spec("MSETNX", -3, true, PAIRS),
+    spec("PSETEX", 4, true, ONE),
+    spec("SET", -3, true, ONE),
+    spec("SETEX", 4, true, ONE),
+    spec("SETNX", 3, true, ONE),
+    spec("SETRANGE", 4, true, ONE),
+    spec("STRLEN", 2, false, ONE),
+    spec("SUBSTR", 4, false, ONE),
+    // bitmaps
+    spec("BITCOUNT", -2, false, ONE),
+    spec("BITFIELD", -2, true, ONE),
+    spec("BITFIELD_RO", -2, false, ONE),
+    spec("BITOP", -4, true, FROM_SECOND),
+    spec("BITPOS", -3, false, ONE),
+    spec("GETBIT", 3, false, ONE),
+    spec("SETBIT", 4, true, ONE),
+    // generic
+    spec("COPY", -3, true, TWO),
+    spec("DEL", -2, true, ALL),
+    spec("DUMP", 2, false, ONE),
+    spec("EXISTS", -2, false, ALL),
+    spec("EXPIRE", -3, true, ONE),
+    spec("EXPIREAT", -3, true, ONE),
+    spec("EXPIRETIME", 2, false, ONE),
+    spec("KEYS", 2, false, NONE),
+    spec("OBJECT", -2, false, SECOND),
+    spec("PERSIST", 2, true, ONE),
+    spec("PEXPIRE", -3, true, ONE),
+    spec("PEXPIREAT", -3, true, ONE),
+    spec("PEXPIRETIME", 2, false, ONE),
+    spec("PTTL", 2, false, ONE),
+    spec("RANDOMKEY", 1, false, NONE),
+    spec("RENAME", 3, true, TWO),
+    spec("RENAMENX", 3, true, TWO),
+    spec("RESTORE", -4, true, ONE),
+    spec("SCAN", -2, false, NONE),
+    spec("SORT", -2, true, OPTIONS),
+    spec("SORT_RO", -2, false, OPTIONS),
+    spec("TOUCH", -2, false, ALL),
+    spec("TTL", 2, false, ONE),
+    spec("TYPE", 2, false, ONE),
+    spec("UNLINK", -2, true, ALL),
+    // hashes
+    spec("HDEL", -3, true, ONE),
+    spec("HEXISTS", 3, false, ONE),
+    spec("HGET", 3, false, ONE),
+    spec("HGETALL", 2, false, ONE),
+    spec("HINCRBY", 4, true, ONE),
+    spec("HINCRBYFLOAT", 4, true, ONE),
+    spec("HKEYS", 2, false, ONE),
+    spec("HLEN", 2, false, ONE),
+    spec("HMGET", -3, false, ONE),
+    spec("HMSET", -4, true, ONE),
+    spec("HRANDFIELD", -2, false, ONE),
+    spec("HSCAN", -3, false, ONE),
+    spec("HSET", -4, true, ONE),
+    spec("HSETNX", 4, true, ONE),
+    spec("HSTRLEN", 3, false, ONE),
+    spec("HVALS", 2, false, ONE),
+    // lists
+    blocking_spec("BLMOVE", 6, true, TWO),
+    blocking_spec("BLMPOP", -5, true, NUMKEYS_SECOND),
+    blocking_spec("BLPOP", -3, true, ALL_BUT_LAST),
+    blocking_spec("BRPOP", -3, true, ALL_BUT_LAST),
+    blocking_spec("BRPOPLPUSH", 4, true, TWO),
+    spec("LINDEX", 3, false, ONE),
+    spec("LINSERT", 5, true, ONE),
+    spec("LLEN", 2, false, ONE),
+    spec("LMOVE", 5, true, TWO),
+    spec("LMPOP", -4, true, NUMKEYS_FIRST),
+    spec("LPOP", -2, true, ONE),
+    spec("LPOS", -3, false, ONE),
+    spec("LPUSH", -3, true, ONE),
+    spec("LPUSHX", -3, true, ONE),
+    spec("LRANGE", 4, false, ONE),
+    spec("LREM", 4, true, ONE),
+    spec("LSET", 4, true, ONE),
+    spec("LTRIM", 4, true, ONE),
+    spec("RPOP", -2, true, ONE),
+    spec("RPOPLPUSH", 3, true, TWO),
+    spec("RPUSH", -3, true, ONE),
+    spec("RPUSHX", -3, true, ONE),
+    // sets
+    spec("SADD", -3, true, ONE),
+    spec("SCARD", 2, false, ONE),
+    spec("SDIFF", -2, false, ALL),
+    spec("SDIFFSTORE", -3, true, ALL),
+    spec("SINTER", -2, false, ALL),
+    spec("SINTERCARD", -3, false, NUMKEYS_FIRST),
+    spec("SINTERSTORE", -3, true, ALL),
+    spec("SISMEMBER", 3, false, ONE),
+    spec("SMEMBERS", 2, false, ONE),
+    spec("SMISMEMBER", -3, false, ONE),
+    spec("SMOVE", 4, true, TWO),
+    spec("SPOP", -2, true, ONE),
+    spec("SRANDMEMBER", -2, false, ONE),
+    spec("SREM", -3, true, ONE),
+    spec("SSCAN", -3, false, ONE),
+    spec("SUNION", -2, false, ALL),
+    spec("SUNIONSTORE", -3, true, ALL),
+    // sorted sets
+    blocking_spec("BZMPOP", -5, true, NUMKEYS_SECOND),
+    blocking_spec("BZPOPMAX", -3, true, ALL_BUT_LAST),
+    blocking_spec("BZPOPMIN", -3, true, ALL_BUT_LAST),
+    spec("ZADD", -4, true, ONE),
+    spec("ZCARD", 2, false, ONE),
+    spec("ZCOUNT", 4, false, ONE),
+    spec("ZDIFF", -3, false, NUMKEYS_FIRST),
+    spec("ZDIFFSTORE", -4, true, NUMKEYS_STORE),
+    spec("ZINCRBY", 4, true, ONE),
+    spec("ZINTER", -3, false, NUMKEYS_FIRST),
+    spec("ZINTERCARD", -3, false, NUMKEYS_FIRST),
+    spec("ZINTERSTORE", -4, true, NUMKEYS_STORE),
+    spec("ZLEXCOUNT", 4, false, ONE),
+    spec("ZMPOP", -4, true, NUMKEYS_FIRST),
+    spec("ZMSCORE", -3, false, ONE),
+    spec("ZPOPMAX", -2, true, ONE),
+    spec("ZPOPMIN", -2, true, ONE),
+    spec("ZRANDMEMBER", -2, false, ONE),
+    spec("ZRANGE", -4, false, ONE),
+    spec("ZRANGEBYLEX", -4, false, ONE),
+    spec("ZRANGEBYSCORE", -4, false, ONE),
+    spec("ZRANGESTORE", -5, true, TWO),
+    spec("ZRANK", -3, false, ONE),
+    spec("ZREM", -3, true, ONE),
+    spec("ZREMRANGEBYLEX", 4, true, ONE),
+    spec("ZREMRANGEBYRANK", 4, true, ONE),
+    spec("ZREMRANGEBYSCORE", 4, true, ONE),
+    spec("ZREVRANGE", -4, false, ONE),
+    spec("ZREVRANGEBYLEX", -4, false, ONE),
+    spec("ZREVRANGEBYSCORE", -4, false, ONE),
+    spec("ZREVRANK", -3, false, ONE),
+    spec("ZSCAN", -3, false, ONE),
+    spec("ZSCORE", 3, false, ONE),
+    spec("ZUNION", -3, false, NUMKEYS_FIRST),
+    spec("ZUNIONSTORE", -4, true, NUMKEYS_STORE),
+    // hyperloglog
+    spec("PFADD", -2, true, ONE),
+    spec("PFCOUNT", -2, false, ALL),
+    spec("PFMERGE", -2, true, ALL),
+    // geo
+    spec("GEOADD", -5, true, ONE),
+    spec("GEODIST", -4, false, ONE),
+    spec("GEOHASH", -2, false, ONE),
+    spec("GEOPOS", -2, false, ONE),
+    spec("GEORADIUS", -6, true, OPTIONS),
+    spec("GEORADIUS_RO", -6, false, ONE),
+    spec("GEORADIUSBYMEMBER", -5, true, OPTIONS),
+    spec("GEORADIUSBYMEMBER_RO", -5, false, ONE),
+    spec("GEOSEARCH", -7, false, ONE),
+    spec("GEOSEARCHSTORE", -8, true, TWO),
+    // streams
+    spec("XACK", -4, true, ONE),
+    spec("XADD", -5, true, ONE),
+    spec("XAUTOCLAIM", -6, true, ONE),
+    spec("XCLAIM", -6, true, ONE),
+    spec("XDEL", -3, true, ONE),
+    spec("XGROUP", -2, true, SECOND),
+    spec("XINFO", -2, false, SECOND),
+    spec("XLEN", 2, false, ONE),
+    spec("XPENDING", -3, false, ONE),
+    spec("XRANGE", -4, false, ONE),
+    blocking_spec("XREAD", -4, false, KeySpec::Streams),
+    blocking_spec("XREADGROUP", -7, true, KeySpec::Streams),
+    spec("XREVRANGE", -4, false, ONE),
+    spec("XTRIM", -4, true, ONE),
+    // scripting and functions
+    spec("EVAL", -3, true, NUMKEYS_SECOND),
+    spec("EVAL_RO", -3, false, NUMKEYS_SECOND),
+    spec("EVALSHA", -3, true, NUMKEYS_SECOND),
+    spec("EVALSHA_RO", -3, false, NUMKEYS_SECOND),
+    spec("FCALL", -3, true, NUMKEYS_SECOND),
+    spec("FCALL_RO", -3, false, NUMKEYS_SECOND),
+    spec("FUNCTION", -2, true, NONE),
+    spec("SCRIPT", -2, false, NONE),
+    // pub/sub
+    spec("PUBLISH", 3, false, NONE),
+    // transactions
+    spec("DISCARD", 1, false, NONE),
+    spec("EXEC", 1, false, NONE),
+    spec("MULTI", 1, false, NONE),
+    spec("UNWATCH", 1, false, NONE),
+    spec("WATCH", -2, false, ALL),
+    // connection and server
+    spec("BGSAVE", -1, true, NONE),
+    spec("CLIENT", -2, false, NONE),
+    spec("COMMAND", -1, false, NONE),
+    spec("CONFIG", -2, true, NONE),
+    spec("DBSIZE", 1, false, NONE),
+    spec("DEBUG", -2, true, NONE),
+    spec("ECHO", 2, false, NONE),
+    spec("FLUSHALL", -1, true, NONE),
+    spec("FLUSHDB", -1, true, NONE),
+    spec("INFO", -1, false, NONE),
+    spec("LASTSAVE", 1, false, NONE),
+    spec("MONITOR", 1, false, NONE),
+    spec("PING", -1, false, NONE),
+    spec("SAVE", 1, true, NONE),
+    spec("SHUTDOWN", -1, true, NONE),
+    spec("TIME", 1, false, NONE),
+    blocking_spec("WAIT", 3, false, NONE),
+    blocking_spec("WAITAOF", 4, false, NONE),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::lookup;
+    use crate::models::{Argument, Command};
+
+    fn command(name: &str, args: &[&str]) -> Command {
+        Command {
+            name: name.to_string(),
+            args: args
+                .iter()
+                .map(|arg| Argument::from(&arg.to_string()))
+                .collect(),
+        }
+    }
+
+    fn keys(name: &str, args: &[&str]) -> Vec<usize> {
+        lookup(name).unwrap().key_indices(&command(name, args))
+    }
+
+    #[test]
+    fn test_range_keys() {
+        assert_eq!(keys("get", &["a"]), vec![0]);
+        assert_eq!(keys("mset", &["a", "1", "b", "2"]), vec![0, 2]);
+        assert_eq!(keys("blpop", &["a", "b", "0"]), vec![0, 1]);
+        assert_eq!(keys("ping", &[]), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_movable_keys() {
+        assert_eq!(keys("eval", &["return 1", "2", "a", "b", "c"]), vec![2, 3]);
+        assert_eq!(keys("zunionstore", &["dest", "2", "a", "b"]), vec![0, 2, 3]);
+        assert_eq!(
+            keys("xread", &["COUNT", "1", "STREAMS", "a", "b", "0", "0"]),
+            vec![3, 4]
+        );
+    }
+}
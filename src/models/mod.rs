@@ -5,10 +5,15 @@ pub mod api_input_data;
 // pub mod api_response;
 pub mod api_types;
 pub mod argument;
+pub mod auth_context;
+pub mod client_certificate;
 pub mod command;
+pub mod command_table;
 pub mod multi_api_input_data;
 pub mod response_builder;
 pub use api_error::ApiError;
 
 pub use argument::Argument;
+pub use auth_context::{AuthContext, RedisUser};
+pub use client_certificate::ClientCertificate;
 pub use command::Command;
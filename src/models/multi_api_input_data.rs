@@ -1,7 +1,7 @@
 use axum::{
     async_trait,
     body::{Body, Bytes},
-    extract::{FromRequest, Request},
+    extract::{rejection::BytesRejection, FromRequest, Request},
     http::header::HeaderName,
     http::HeaderValue,
     response::Response,
@@ -9,7 +9,7 @@ use axum::{
 
 use serde::Deserialize;
 
-use super::api_types::JsonValue;
+use super::{api_input_data::body_rejection, api_types::JsonValue};
 
 #[derive(Deserialize, Debug)]
 pub struct MultiApiInput(pub Vec<Vec<JsonValue>>);
@@ -17,7 +17,7 @@ pub struct MultiApiInput(pub Vec<Vec<JsonValue>>);
 #[async_trait]
 impl<S> FromRequest<S> for MultiApiInput
 where
-    Bytes: FromRequest<S>,
+    Bytes: FromRequest<S, Rejection = BytesRejection>,
     S: Send + Sync,
 {
     type Rejection = Response;
@@ -27,40 +27,28 @@ where
         let content_type = req.headers().get(HeaderName::from_static("content-type"));
 
         match content_type {
-            None => {
-                return Err(Response::builder()
-                    .status(400)
-                    .body(Body::from("invalid content type"))
-                    .unwrap());
-            }
-            Some(content_type) => {
-                if content_type == HeaderValue::from_static("application/json") {
-                    let body = Bytes::from_request(req, state).await.map_err(|_| {
+            Some(content_type) if content_type == HeaderValue::from_static("application/json") => {
+                let body = Bytes::from_request(req, state)
+                    .await
+                    .map_err(body_rejection)?;
+
+                // parse json from bytes
+
+                let deserialized = serde_json::from_slice(&body)
+                    .map_err(|e| {
                         Response::builder()
                             .status(400)
-                            .body(Body::from("invalid body"))
+                            .body(Body::from(format!("invalid json: {}", e)))
                             .unwrap()
-                    })?;
-
-                    // parse json from bytes
-
-                    let deserialized = serde_json::from_slice(&body)
-                        .map_err(|e| {
-                            Response::builder()
-                                .status(400)
-                                .body(Body::from(format!("invalid json: {}", e)))
-                                .unwrap()
-                        })
-                        .map(|s| MultiApiInput(s))?;
+                    })
+                    .map(MultiApiInput)?;
 
-                    return Ok(deserialized);
-                } else {
-                    return Err(Response::builder()
-                        .status(400)
-                        .body(Body::from("invalid content type"))
-                        .unwrap());
-                }
+                Ok(deserialized)
             }
+            _ => Err(Response::builder()
+                .status(400)
+                .body(Body::from("invalid content type"))
+                .unwrap()),
         }
     }
 }
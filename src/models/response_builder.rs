@@ -1,4 +1,8 @@
 use crate::utils::redis_value_to_json;
+use axum::{
+    response::{IntoResponse, Response},
+    Json,
+};
 use serde::Serialize;
 
 use super::{
@@ -112,6 +116,26 @@ impl ResponseBuilder {
         }
     }
 
+    /// Builds the HTTP response for the result of a single command, converting a successful
+    /// reply with `convert`. Errors raised before reaching Redis keep their own status and headers.
+    pub fn respond(
+        &self,
+        result: RedisResponse,
+        convert: impl FnOnce(RedisValue, &str) -> JsonValue,
+    ) -> Response {
+        match result {
+            Ok(value) => Json(ApiResponse {
+                result: Some(convert(value, &self.encoding)),
+                error: None,
+            })
+            .into_response(),
+            Err(error @ ApiError::RedisError(_)) => {
+                (error.status_code(), Json(self.build(Err(error)))).into_response()
+            }
+            Err(error) => error.into_response(),
+        }
+    }
+
     /// Creates an ApiResponse indicating an error, based solely on the ApiError provided.
     /// This is useful for generating error responses without a specific Redis value.
     pub fn error(error: ApiError) -> ApiResponse {
@@ -123,7 +147,7 @@ impl ResponseBuilder {
 
     /// Creates an ApiResponse with a successful result, represented as a string.
     /// This can be used for simple string responses or messages mostly for testing purposes.
-    pub fn from_str(result: &str) -> ApiResponse {
+    pub fn text(result: &str) -> ApiResponse {
         ApiResponse {
             result: Some(JsonValue::String(result.to_string())),
             error: None,
@@ -1,26 +1,36 @@
 use axum::{body::Body, routing::get, Router};
 
-use super::{pipeline_routes, redis_routes, transaction_routes};
+use super::{
+    command_routes, function_routes, pipeline_routes, redis_routes, script_routes, stream_routes,
+    transaction_routes,
+};
 
 pub fn app_routes() -> Router {
-    return Router::new()
+    Router::new()
         .route(
             "/",
             get(|| async { Body::from(serde_json::json!({"status": "working",}).to_string()) }),
         )
         .merge(redis_routes())
         .merge(pipeline_routes())
-        .merge(transaction_routes());
+        .merge(transaction_routes())
+        .merge(stream_routes())
+        .merge(script_routes())
+        .merge(function_routes())
+        .merge(command_routes())
 }
 
 #[cfg(test)]
 mod tests {
-    use axum::http::{header, HeaderValue, StatusCode};
+    use std::sync::Arc;
+
+    use axum::http::{header, HeaderValue, Method, StatusCode};
     use axum_test::TestServer;
     use clap::Parser;
 
     use super::app_routes;
     use crate::cmd::Args;
+    use crate::config::{CorsConfig, DEFAULT_CORS_HEADERS};
     use crate::utils::add_layers;
     use crate::utils::app_setup::app_setup;
 
@@ -104,4 +114,58 @@ mod tests {
             "status": "working"
         }));
     }
+
+    #[tokio::test]
+    async fn test_cors_preflight() {
+        let (_, app_state) = app_setup(Args::parse());
+
+        let mut app_state = (*app_state).clone();
+        app_state.cors = CorsConfig {
+            allowed_origins: vec!["https://app.example.com".to_string()],
+            allowed_methods: vec!["GET".to_string(), "POST".to_string()],
+            allowed_headers: DEFAULT_CORS_HEADERS.iter().map(|h| h.to_string()).collect(),
+            allow_credentials: false,
+            max_age: 600,
+        };
+
+        let server = TestServer::new(add_layers(app_routes(), Arc::new(app_state))).unwrap();
+
+        let response = server
+            .method(Method::OPTIONS, "/")
+            .add_header(
+                header::ORIGIN,
+                HeaderValue::from_static("https://app.example.com"),
+            )
+            .add_header(
+                header::ACCESS_CONTROL_REQUEST_METHOD,
+                HeaderValue::from_static("POST"),
+            )
+            .add_header(
+                header::ACCESS_CONTROL_REQUEST_HEADERS,
+                HeaderValue::from_static("authorization, upstash-encoding"),
+            )
+            .await;
+
+        response.assert_status(StatusCode::OK);
+        assert_eq!(
+            response.header(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            "https://app.example.com"
+        );
+        assert_eq!(response.header(header::ACCESS_CONTROL_MAX_AGE), "600");
+
+        // Rejected requests still carry the headers browsers need to read the error
+        let response = server
+            .get("/")
+            .add_header(
+                header::ORIGIN,
+                HeaderValue::from_static("https://app.example.com"),
+            )
+            .await;
+
+        response.assert_status(StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.header(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            "https://app.example.com"
+        );
+    }
 }
@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use crate::{
+    models::{
+        api_types::JsonValue, command_table::CommandSpec, response_builder::ApiResponse, ApiError,
+        AuthContext, Command,
+    },
+    state::AppState,
+};
+use axum::{
+    extract::Path,
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Json, Router,
+};
+
+fn result(result: JsonValue) -> Response {
+    Json(ApiResponse {
+        result: Some(result),
+        error: None,
+    })
+    .into_response()
+}
+
+/// Whether the caller may run the command, regardless of the keys it would be given.
+/// Subcommand entries are checked as the container command followed by the subcommand.
+fn allowed(app_state: &AppState, auth: &AuthContext, spec: &CommandSpec) -> bool {
+    let mut parts = spec.name.split('|');
+    let name = parts.next().unwrap_or_default();
+    let command = Command::from_strings(name, parts.map(String::from));
+
+    app_state.policy.check(&command, auth).is_ok()
+}
+
+fn describe(app_state: &AppState, auth: &AuthContext, spec: &CommandSpec) -> JsonValue {
+    serde_json::json!({
+        "name": spec.name,
+        "arity": spec.arity,
+        "flags": spec.flags,
+        "keys": spec.keys,
+        "write": spec.write,
+        "blocking": spec.blocking,
+        "acl_categories": spec.acl_categories,
+        "allowed": allowed(app_state, auth, spec),
+    })
+}
+
+/// Lists the commands rediserve knows, with the summary of their documentation.
+pub async fn list_commands_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+) -> Response {
+    let commands = app_state
+        .commands
+        .commands()
+        .iter()
+        .map(|spec| {
+            let mut command = describe(&app_state, &auth, spec);
+            let docs = app_state.commands.docs(&spec.name);
+
+            command["summary"] = docs
+                .as_ref()
+                .and_then(|docs| docs.get("summary").cloned())
+                .unwrap_or_default();
+            command["group"] = docs
+                .as_ref()
+                .and_then(|docs| docs.get("group").cloned())
+                .unwrap_or_default();
+            command
+        })
+        .collect();
+
+    result(JsonValue::Array(commands))
+}
+
+/// Describes a command with its full documentation. Subcommands are named as
+/// `container|subcommand` or `container subcommand`.
+pub async fn get_command_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    Path(name): Path<String>,
+) -> Response {
+    let name = name.trim().replace(' ', "|");
+
+    let Some(spec) = app_state.commands.lookup(&name) else {
+        return ApiError::UnknownCommand(name).into_response();
+    };
+
+    let mut command = describe(&app_state, &auth, &spec);
+    command["docs"] = app_state.commands.docs(&spec.name).unwrap_or_default();
+
+    result(command)
+}
+
+pub fn command_routes() -> Router {
+    Router::new()
+        .route("/commands", get(list_commands_handler))
+        .route("/commands/:name", get(get_command_handler))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use axum_test::TestServer;
+    use clap::Parser;
+
+    use super::command_routes;
+    use crate::cmd::Args;
+    use crate::utils::add_layers;
+    use crate::utils::app_setup::app_setup;
+
+    #[tokio::test]
+    async fn test_describe_commands() {
+        let (config, app_state) = app_setup(Args::parse());
+        let token = config.token.unwrap();
+
+        let server = TestServer::new(add_layers(command_routes(), app_state)).unwrap();
+
+        let response = server
+            .get("/commands/get")
+            .add_query_param("_token", &token)
+            .await;
+        response.assert_status(StatusCode::OK);
+
+        let command = &response.json::<serde_json::Value>()["result"];
+        assert_eq!(command["name"], "GET");
+        assert_eq!(command["keys"]["type"], "range");
+        assert_eq!(command["allowed"], true);
+
+        server
+            .get("/commands/nosuchcommand")
+            .add_query_param("_token", &token)
+            .await
+            .assert_status(StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,241 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Extension, Json, Router,
+};
+use base64::{engine, prelude::*};
+use serde::Deserialize;
+
+use crate::{
+    models::{
+        api_input_data::{CallInput, ExtractEncoding},
+        api_types::{JsonValue, RedisValue},
+        response_builder::ResponseBuilder,
+        ApiError, Argument, AuthContext, Command,
+    },
+    services::CommandService,
+    state::AppState,
+    utils::{function_list_to_json, redis_value_to_json},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ListParams {
+    /// Pattern library names must match
+    library: Option<String>,
+    /// Whether the source of each library is included
+    #[serde(default)]
+    withcode: bool,
+}
+
+/// Lists the libraries with their functions.
+pub async fn list_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    encoding: ExtractEncoding,
+    Query(params): Query<ListParams>,
+) -> Response {
+    let mut args = vec!["LIST".to_string()];
+    if let Some(library) = params.library {
+        args.extend(["LIBRARYNAME".to_string(), library]);
+    }
+    if params.withcode {
+        args.push("WITHCODE".to_string());
+    }
+
+    let result =
+        CommandService::execute(&app_state, &auth, Command::from_strings("FUNCTION", args)).await;
+
+    ResponseBuilder::new(encoding.into_inner()).respond(result, function_list_to_json)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoadParams {
+    /// Whether an existing library of the same name is replaced
+    #[serde(default)]
+    replace: bool,
+}
+
+/// Loads the library whose source is the raw request body, responding with its name.
+pub async fn load_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    encoding: ExtractEncoding,
+    Query(params): Query<LoadParams>,
+    source: String,
+) -> Response {
+    let mut args = vec!["LOAD".to_string()];
+    if params.replace {
+        args.push("REPLACE".to_string());
+    }
+    args.push(source);
+
+    let result =
+        CommandService::execute(&app_state, &auth, Command::from_strings("FUNCTION", args)).await;
+
+    ResponseBuilder::new(encoding.into_inner()).respond(result, redis_value_to_json)
+}
+
+pub async fn delete_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    encoding: ExtractEncoding,
+    Path(library): Path<String>,
+) -> Response {
+    let command = Command::from_strings("FUNCTION", ["DELETE".to_string(), library]);
+
+    let result = CommandService::execute(&app_state, &auth, command).await;
+
+    ResponseBuilder::new(encoding.into_inner()).respond(result, redis_value_to_json)
+}
+
+/// Responds with the serialized payload of every library, always base64 encoded as it is binary.
+pub async fn dump_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+) -> Response {
+    let command = Command::from_strings("FUNCTION", ["DUMP".to_string()]);
+
+    let result = CommandService::execute(&app_state, &auth, command).await;
+
+    ResponseBuilder::new("base64".to_string()).respond(result, redis_value_to_json)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RestoreParams {
+    /// APPEND (the default), REPLACE or FLUSH
+    policy: Option<String>,
+}
+
+/// Restores libraries from a base64 encoded payload of `GET /functions/dump` sent as the body.
+pub async fn restore_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    Query(params): Query<RestoreParams>,
+    payload: String,
+) -> Response {
+    let payload = match engine::general_purpose::STANDARD.decode(payload.trim()) {
+        Ok(payload) => payload,
+        Err(_) => {
+            return ApiError::InvalidInput("the payload must be base64 encoded".to_string())
+                .into_response()
+        }
+    };
+
+    let mut args = vec!["RESTORE".to_string()];
+    args.extend(params.policy.map(|policy| policy.to_uppercase()));
+
+    // the payload is binary, so it is checked without it and sent directly
+    let command = Command::from_strings("FUNCTION", args);
+
+    if let Err(error) = CommandService::authorize(&app_state, &auth, &command).await {
+        return error.into_response();
+    }
+
+    let mut con = match app_state.redis_pools.connection(&auth).await {
+        Ok(con) => con,
+        Err(error) => return error.into_response(),
+    };
+
+    let mut cmd = redis::cmd("FUNCTION");
+    cmd.arg("RESTORE").arg(payload);
+    for arg in command.args.iter().skip(1) {
+        cmd.arg(arg);
+    }
+
+    let result = CommandService::with_timeout(app_state.command_timeout, async {
+        cmd.query_async::<_, RedisValue>(&mut con)
+            .await
+            .map_err(ApiError::RedisError)
+    })
+    .await
+    .and_then(|result| result);
+
+    ResponseBuilder::new("utf-8".to_string()).respond(result, redis_value_to_json)
+}
+
+/// Calls a function with FCALL, or FCALL_RO for `/call_ro`, keys and arguments given apart.
+async fn call(
+    app_state: &AppState,
+    auth: &AuthContext,
+    encoding: ExtractEncoding,
+    name: &str,
+    function: String,
+    input: Option<Json<CallInput>>,
+) -> Response {
+    let input = input.map(|Json(input)| input).unwrap_or_default();
+
+    let mut args = vec![Argument(JsonValue::String(function))];
+    args.extend(input.into_arguments());
+
+    let command = Command {
+        name: name.to_string(),
+        args,
+    };
+
+    let result = CommandService::execute(app_state, auth, command).await;
+
+    ResponseBuilder::new(encoding.into_inner()).respond(result, redis_value_to_json)
+}
+
+pub async fn call_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    encoding: ExtractEncoding,
+    Path(function): Path<String>,
+    input: Option<Json<CallInput>>,
+) -> Response {
+    call(&app_state, &auth, encoding, "FCALL", function, input).await
+}
+
+pub async fn call_ro_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    encoding: ExtractEncoding,
+    Path(function): Path<String>,
+    input: Option<Json<CallInput>>,
+) -> Response {
+    call(&app_state, &auth, encoding, "FCALL_RO", function, input).await
+}
+
+pub fn function_routes() -> Router {
+    Router::new()
+        .route("/functions", get(list_handler).post(load_handler))
+        .route("/functions/dump", get(dump_handler))
+        .route("/functions/restore", post(restore_handler))
+        .route("/functions/:name", delete(delete_handler))
+        .route("/functions/:name/call", post(call_handler))
+        .route("/functions/:name/call_ro", post(call_ro_handler))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, Extension};
+    use axum_test::TestServer;
+    use clap::Parser;
+
+    use super::function_routes;
+    use crate::cmd::Args;
+    use crate::models::AuthContext;
+    use crate::utils::app_setup::app_setup;
+
+    #[tokio::test]
+    async fn test_call_allowed_by_default_policy() {
+        let (_, app_state) = app_setup(Args::parse());
+        let app = function_routes()
+            .layer(Extension(AuthContext::default()))
+            .layer(Extension(app_state));
+        let server = TestServer::new(app).unwrap();
+
+        let response = server
+            .post("/functions/enqueue/call")
+            .json(&serde_json::json!({ "keys": ["jobs"], "args": ["job"] }))
+            .await;
+        assert_ne!(response.status_code(), StatusCode::FORBIDDEN);
+
+        let response = server.post("/functions/enqueue/call_ro").await;
+        assert_ne!(response.status_code(), StatusCode::FORBIDDEN);
+    }
+}
@@ -1,9 +1,17 @@
 pub mod app_route;
+pub mod command_route;
+pub mod function_route;
 pub mod pipeline_route;
 pub mod redis_route;
+pub mod script_route;
+pub mod stream_route;
 pub mod transaction_route;
 
 pub use app_route::app_routes;
+pub use command_route::command_routes;
+pub use function_route::function_routes;
 pub use pipeline_route::pipeline_routes;
 pub use redis_route::redis_routes;
+pub use script_route::script_routes;
+pub use stream_route::stream_routes;
 pub use transaction_route::transaction_routes;
@@ -1,4 +1,9 @@
-use axum::{response::IntoResponse, routing::post, Json};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json,
+};
 use std::sync::Arc;
 
 use axum::{Extension, Router};
@@ -6,17 +11,26 @@ use axum::{Extension, Router};
 use crate::{
     models::{
         api_input_data::ExtractEncoding, api_types::RedisResponse,
-        multi_api_input_data::MultiApiInput, response_builder::ResponseBuilder, Argument, Command,
+        multi_api_input_data::MultiApiInput, response_builder::ResponseBuilder, Argument,
+        AuthContext, Command,
+    },
+    services::{
+        blocking_commands::BLOCKING_TIMEOUT_MARGIN, command_service::PreparedCommand,
+        CommandService,
     },
-    services::CommandService,
     state::AppState,
 };
 
 pub async fn pipeline_route_handler(
     Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
     encoding: ExtractEncoding,
     payload: MultiApiInput,
-) -> impl IntoResponse {
+) -> Response {
+    if let Err(error) = app_state.limits.check_commands(payload.0.len()) {
+        return error.into_response();
+    }
+
     // loop through the payload and process the data
 
     let mut command_list: Vec<Command> = vec![];
@@ -28,7 +42,7 @@ pub async fn pipeline_route_handler(
 
         let command_str = data[0].to_string().trim_matches('\"').to_string();
         if data.len() > 1 {
-            let arguments = data.iter().skip(1).map(|s| Argument::from(s)).collect();
+            let arguments = data.iter().skip(1).map(Argument::from).collect();
             let command = Command {
                 name: command_str,
                 args: arguments,
@@ -43,12 +57,71 @@ pub async fn pipeline_route_handler(
         }
     }
 
-    let result: Vec<RedisResponse> =
-        CommandService::process_pipeline(command_list, app_state.redis_pool.clone()).await;
+    let prepared: Vec<PreparedCommand> = match command_list
+        .into_iter()
+        .map(|command| CommandService::prepare(&app_state, command))
+        .collect()
+    {
+        Ok(prepared) => prepared,
+        Err(error) => return error.into_response(),
+    };
+
+    if let Err(error) = app_state
+        .policy
+        .check_all(prepared.iter().map(|prepared| &prepared.command), &auth)
+    {
+        return error.into_response();
+    }
+
+    if let Err(error) = app_state
+        .rate_limiter
+        .check_commands(&auth, prepared.len(), &app_state.redis_pool)
+        .await
+    {
+        return error.into_response();
+    }
+
+    // the pipeline may take as long as its longest blocking command
+    let timeout = match prepared.iter().filter_map(|prepared| prepared.block).max() {
+        Some(block) => Some(block + BLOCKING_TIMEOUT_MARGIN),
+        None => app_state.command_timeout,
+    };
+
+    let (pool, blocking_pool) = match (
+        app_state.redis_pools.get(&auth),
+        app_state.blocking_pools.get(&auth),
+    ) {
+        (Ok(pool), Ok(blocking_pool)) => (pool, blocking_pool),
+        (Err(error), _) | (_, Err(error)) => return error.into_response(),
+    };
+
+    let result: Vec<RedisResponse> = match CommandService::with_timeout(
+        timeout,
+        CommandService::process_pipeline(prepared, pool, blocking_pool),
+    )
+    .await
+    {
+        Ok(result) => result,
+        Err(error) => return error.into_response(),
+    };
+
+    if let Err(error) = app_state.limits.check_replies(&result) {
+        return error.into_response();
+    }
+
+    // results are returned per command, but a permission error from Redis rejects the request
+    let status = match result
+        .iter()
+        .filter_map(|result| result.as_ref().err())
+        .find(|error| error.status_code() == StatusCode::FORBIDDEN)
+    {
+        Some(error) => error.status_code(),
+        None => StatusCode::OK,
+    };
 
     let response = ResponseBuilder::new(encoding.into_inner()).build_pipeline(result);
 
-    return Json(response);
+    (status, Json(response)).into_response()
 }
 pub fn pipeline_routes() -> Router {
     Router::new().route("/pipeline", post(pipeline_route_handler))
@@ -107,4 +180,34 @@ mod tests {
 
         response.assert_status(StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn test_pipeline_dangerous_command_blocked() {
+        let args = Args::parse();
+
+        let (config, app_state) = app_setup(args);
+
+        let routes = pipeline_routes();
+
+        let app = add_layers(routes, app_state);
+
+        let token = config.token.unwrap();
+
+        let server = match TestServer::new(app) {
+            Ok(server) => server,
+            Err(e) => panic!("Error setting up test server: {}", e),
+        };
+
+        let response = server
+            .post("/pipeline")
+            .json(&serde_json::json!([
+                ["MULTI"],
+                ["CONFIG", "SET", "maxmemory", "1"],
+                ["EXEC"]
+            ]))
+            .add_query_param("_token", &token)
+            .await;
+
+        response.assert_status(StatusCode::FORBIDDEN);
+    }
 }
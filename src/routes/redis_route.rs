@@ -2,14 +2,16 @@ use crate::{
     models::{
         api_input_data::{ApiInput, ApiInputValue, ExtractEncoding},
         response_builder::ResponseBuilder,
-        ApiError, Argument, Command,
+        ApiError, Argument, AuthContext, Command,
     },
     services::CommandService,
+    utils::http_cache,
 };
 use axum::{
     extract::Json,
     extract::{Path, Query},
-    response::IntoResponse,
+    http::{HeaderMap, Method, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Extension, Router,
 };
@@ -18,13 +20,17 @@ use std::{collections::HashMap, sync::Arc};
 
 use crate::state::AppState;
 
+#[allow(clippy::too_many_arguments)]
 pub async fn command_route_handler(
     Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
     encoding: ExtractEncoding,
+    method: Method,
+    headers: HeaderMap,
     path_segments: Option<Path<String>>,
     params: Query<HashMap<String, String>>,
     payload: ApiInput,
-) -> impl IntoResponse {
+) -> Response {
     let mut command_str = String::new();
     let mut arguements: Vec<Argument> = Vec::new();
 
@@ -39,7 +45,7 @@ pub async fn command_route_handler(
             command_str = path_vaues[0].to_string();
 
             if path_vaues.len() > 1 {
-                arguements.extend(path_vaues.iter().skip(1).map(|s| Argument::from(s)));
+                arguements.extend(path_vaues.iter().skip(1).map(Argument::from));
             }
         }
         true
@@ -56,28 +62,28 @@ pub async fn command_route_handler(
         ApiInputValue::List(command_value_list) => {
             if !path_segments_present {
                 if command_value_list.is_empty() {
-                    return Json(ResponseBuilder::error(ApiError::NoCommand));
+                    return ApiError::NoCommand.into_response();
                 } else {
                     // remove new line character
                     command_str = command_value_list[0]
                         .to_string()
                         .trim_matches('\"')
                         .to_string();
-                    arguements.extend(command_value_list.iter().skip(1).map(|s| Argument::from(s)));
+                    arguements.extend(command_value_list.iter().skip(1).map(Argument::from));
                 }
             }
         }
         ApiInputValue::None => {
             if !path_segments_present {
                 // return Json(ApiResponse::from(Err(ApiError::NoCommand)));
-                return Json(ResponseBuilder::error(ApiError::NoCommand));
+                return ApiError::NoCommand.into_response();
             }
         }
     }
 
     if command_str.is_empty() {
         // return Json(ApiResponse::from(Err(ApiError::NoCommand)));
-        return Json(ResponseBuilder::error(ApiError::NoCommand));
+        return ApiError::NoCommand.into_response();
     }
 
     for (key, value) in params.iter() {
@@ -91,13 +97,44 @@ pub async fn command_route_handler(
         args: arguements,
     };
 
-    let con = app_state.redis_pool.get().await.unwrap();
+    // path-style reads answer with an ETag and Cache-Control headers
+    let cached_keys = if method == Method::GET {
+        app_state.commands.read_keys(&command)
+    } else {
+        None
+    };
 
-    let result = CommandService::process_command(command, con).await;
+    let result = match CommandService::execute(&app_state, &auth, command).await {
+        // errors raised before reaching Redis carry their own status and headers
+        Err(error) if !matches!(error, ApiError::RedisError(_)) => return error.into_response(),
+        result => result,
+    };
+
+    let status = match &result {
+        Err(error) => error.status_code(),
+        Ok(_) => StatusCode::OK,
+    };
 
+    let succeeded = result.is_ok();
     let response = ResponseBuilder::new(encoding.into_inner()).build(result);
 
-    return response.into();
+    if let (Some(keys), true) = (cached_keys, succeeded) {
+        if let (Ok(body), Ok(pool)) = (
+            serde_json::to_vec(&response),
+            app_state.redis_pools.get(&auth),
+        ) {
+            return http_cache::conditional_response(
+                &app_state.http_cache,
+                &pool,
+                &keys,
+                &headers,
+                body,
+            )
+            .await;
+        }
+    }
+
+    (status, Json(response)).into_response()
 }
 
 pub fn redis_routes() -> Router {
@@ -110,12 +147,15 @@ pub fn redis_routes() -> Router {
 #[cfg(test)]
 mod tests {
 
-    use axum::http::StatusCode;
+    use std::sync::Arc;
+
+    use axum::http::{header, HeaderValue, StatusCode};
     use axum_test::TestServer;
     use clap::Parser;
 
     use super::redis_routes;
     use crate::cmd::Args;
+    use crate::config::HttpCacheConfig;
     use crate::utils::add_layers;
     use crate::utils::app_setup::app_setup;
     use rand::Rng;
@@ -278,4 +318,99 @@ mod tests {
             "result": random_value
         }));
     }
+
+    #[tokio::test]
+    async fn test_dangerous_command_blocked() {
+        let args = Args::parse();
+
+        let (config, app_state) = app_setup(args);
+
+        let routes = redis_routes();
+
+        let app = add_layers(routes, app_state);
+
+        let token = config.token.unwrap();
+
+        let server = match TestServer::new(app) {
+            Ok(server) => server,
+            Err(e) => panic!("Error setting up test server: {}", e),
+        };
+
+        let response = server
+            .get("/flushall")
+            .add_query_param("_token", &token)
+            .await;
+
+        response.assert_status(StatusCode::FORBIDDEN);
+
+        response.assert_json(&serde_json::json!({
+            "error": "Command not allowed: FLUSHALL"
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_conditional_get() {
+        let (config, app_state) = app_setup(Args::parse());
+        let token = config.token.unwrap();
+
+        let mut app_state = (*app_state).clone();
+        app_state.http_cache = HttpCacheConfig {
+            max_age: Some(60),
+            public: true,
+        };
+
+        let server = TestServer::new(add_layers(redis_routes(), Arc::new(app_state))).unwrap();
+
+        let random_key: String = rand::thread_rng()
+            .sample_iter(&rand::distributions::Alphanumeric)
+            .take(30)
+            .map(char::from)
+            .collect();
+
+        server
+            .get(format!("/set/{}/on", random_key).as_str())
+            .add_query_param("_token", &token)
+            .await
+            .assert_status(StatusCode::OK);
+
+        let response = server
+            .get(format!("/get/{}", random_key).as_str())
+            .add_query_param("_token", &token)
+            .await;
+        response.assert_status(StatusCode::OK);
+        assert!(response
+            .header(header::CACHE_CONTROL)
+            .to_str()
+            .unwrap()
+            .starts_with("public"));
+        assert_eq!(
+            response.header(header::VARY),
+            "Authorization, Upstash-Encoding, Rediserve-Encoding"
+        );
+
+        let etag = response.headers()[header::ETAG].clone();
+
+        let response = server
+            .get(format!("/get/{}", random_key).as_str())
+            .add_query_param("_token", &token)
+            .add_header(header::IF_NONE_MATCH, etag)
+            .await;
+        response.assert_status(StatusCode::NOT_MODIFIED);
+        assert!(response.headers().contains_key(header::VARY));
+
+        // a shared cache must not serve a bearer's read to other callers
+        let response = server
+            .get(format!("/get/{}", random_key).as_str())
+            .add_header(
+                header::AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+            )
+            .await;
+        response.assert_status(StatusCode::OK);
+        assert!(response
+            .header(header::CACHE_CONTROL)
+            .to_str()
+            .unwrap()
+            .starts_with("private"));
+    }
 }
@@ -0,0 +1,227 @@
+use std::sync::Arc;
+
+use crate::{
+    models::{
+        api_input_data::{CallInput, ExtractEncoding},
+        api_types::JsonValue,
+        response_builder::{ApiResponse, ResponseBuilder},
+        ApiError, Argument, AuthContext, Command,
+    },
+    services::{
+        script_registry::{self, RegisteredScript, EVALNAME},
+        CommandService,
+    },
+    state::AppState,
+    utils::redis_value_to_json,
+};
+use axum::{
+    extract::Path,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+
+fn result(result: JsonValue) -> Response {
+    Json(ApiResponse {
+        result: Some(result),
+        error: None,
+    })
+    .into_response()
+}
+
+/// Scripts are managed by callers allowed to run `SCRIPT LOAD`.
+fn load_command(source: &str) -> Command {
+    Command::from_strings("SCRIPT", ["LOAD".to_string(), source.to_string()])
+}
+
+/// Registered scripts read and write keys regardless of the caller's restrictions, so they
+/// are only shown to callers allowed to run `SCRIPT EXISTS` without a key prefix or read-only access.
+fn check_read(app_state: &AppState, auth: &AuthContext) -> Result<(), ApiError> {
+    let command = Command::from_strings("SCRIPT", ["EXISTS".to_string()]);
+
+    app_state.policy.check(&command, auth)?;
+
+    if auth.read_only || auth.key_prefix.is_some() {
+        return Err(ApiError::CommandNotAllowed("SCRIPT EXISTS".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Lists the registered scripts with their digests.
+pub async fn list_scripts_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+) -> Response {
+    if let Err(error) = check_read(&app_state, &auth) {
+        return error.into_response();
+    }
+
+    let scripts = app_state
+        .scripts
+        .list()
+        .iter()
+        .map(|script| serde_json::json!({ "name": script.name, "sha": script.sha }))
+        .collect();
+
+    result(JsonValue::Array(scripts))
+}
+
+pub async fn get_script_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    Path(name): Path<String>,
+) -> Response {
+    if let Err(error) = check_read(&app_state, &auth) {
+        return error.into_response();
+    }
+
+    match app_state.scripts.get(&name) {
+        Some(script) => result(serde_json::json!({
+            "name": script.name,
+            "sha": script.sha,
+            "source": script.source,
+        })),
+        None => ApiError::UnknownScript(name).into_response(),
+    }
+}
+
+/// Registers the request body as the script's source, replacing any previous version.
+/// The script is loaded into Redis first, so a script that does not compile is rejected.
+pub async fn put_script_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    encoding: ExtractEncoding,
+    Path(name): Path<String>,
+    source: String,
+) -> Response {
+    if let Err(error) = script_registry::validate_name(&name) {
+        return ApiError::InvalidScript(error).into_response();
+    }
+
+    if source.trim().is_empty() {
+        return ApiError::InvalidScript("the script is empty".to_string()).into_response();
+    }
+
+    let result = CommandService::execute(&app_state, &auth, load_command(&source))
+        .await
+        .inspect(|_| {
+            let script = app_state
+                .scripts
+                .insert(RegisteredScript::new(&name, &source));
+
+            tracing::info!(script = %script.name, sha = %script.sha, "registered script");
+        });
+
+    ResponseBuilder::new(encoding.into_inner()).respond(result, redis_value_to_json)
+}
+
+pub async fn delete_script_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    Path(name): Path<String>,
+) -> Response {
+    if let Err(error) = app_state.policy.check(&load_command(""), &auth) {
+        return error.into_response();
+    }
+
+    match app_state.scripts.remove(&name) {
+        Some(_) => result(JsonValue::String("OK".to_string())),
+        None => ApiError::UnknownScript(name).into_response(),
+    }
+}
+
+/// Runs a registered script, the same as `["EVALNAME", name, numkeys, ...keys, ...args]`.
+pub async fn run_script_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    encoding: ExtractEncoding,
+    Path(name): Path<String>,
+    input: Option<Json<CallInput>>,
+) -> Response {
+    let input = input.map(|Json(input)| input).unwrap_or_default();
+
+    let mut args = vec![Argument(JsonValue::String(name))];
+    args.extend(input.into_arguments());
+
+    let command = Command {
+        name: EVALNAME.to_string(),
+        args,
+    };
+
+    let result = CommandService::execute(&app_state, &auth, command).await;
+
+    ResponseBuilder::new(encoding.into_inner()).respond(result, redis_value_to_json)
+}
+
+pub fn script_routes() -> Router {
+    Router::new()
+        .route("/scripts", get(list_scripts_handler))
+        .route(
+            "/scripts/:name",
+            get(get_script_handler)
+                .put(put_script_handler)
+                .delete(delete_script_handler),
+        )
+        .route("/scripts/:name/run", post(run_script_handler))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{http::StatusCode, Extension};
+    use axum_test::TestServer;
+    use clap::Parser;
+
+    use super::script_routes;
+    use crate::cmd::Args;
+    use crate::models::AuthContext;
+    use crate::services::script_registry::RegisteredScript;
+    use crate::utils::app_setup::app_setup;
+
+    #[tokio::test]
+    async fn test_read_scripts_requires_unrestricted_caller() {
+        let (_, app_state) = app_setup(Args::parse());
+        app_state
+            .scripts
+            .insert(RegisteredScript::new("counter", "return 1"));
+
+        let server = |auth: AuthContext| {
+            let app = script_routes()
+                .layer(Extension(auth))
+                .layer(Extension(app_state.clone()));
+            TestServer::new(app).unwrap()
+        };
+
+        let unrestricted = server(AuthContext::default());
+        unrestricted
+            .get("/scripts")
+            .await
+            .assert_status(StatusCode::OK);
+        unrestricted
+            .get("/scripts/counter")
+            .await
+            .assert_status(StatusCode::OK);
+
+        let read_only = server(AuthContext {
+            read_only: true,
+            ..Default::default()
+        });
+        read_only
+            .get("/scripts")
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+
+        let prefixed = server(AuthContext {
+            key_prefix: Some("jobs:".to_string()),
+            ..Default::default()
+        });
+        prefixed
+            .get("/scripts/counter")
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+        prefixed
+            .post("/scripts/counter/run")
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,376 @@
+use std::{convert::Infallible, sync::Arc};
+
+use axum::{
+    extract::{Path, Query},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+
+use crate::{
+    models::{
+        api_input_data::ExtractEncoding,
+        api_types::{JsonValue, RedisValue},
+        response_builder::ResponseBuilder,
+        ApiError, AuthContext, Command,
+    },
+    services::CommandService,
+    state::AppState,
+    utils::{redis_value_to_json, stream_entries_to_json, stream_read_to_json},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGroupInput {
+    /// ID the group starts reading after, `$` for new entries only and `0` for the whole stream
+    #[serde(default = "default_start")]
+    start: String,
+    /// Whether the stream is created if it does not exist
+    #[serde(default = "default_mkstream")]
+    mkstream: bool,
+}
+
+fn default_start() -> String {
+    "$".to_string()
+}
+
+fn default_mkstream() -> bool {
+    true
+}
+
+/// Creates a consumer group, succeeding if it already exists.
+pub async fn create_group_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    encoding: ExtractEncoding,
+    Path((stream, group)): Path<(String, String)>,
+    input: Option<Json<CreateGroupInput>>,
+) -> Response {
+    let input = input.map(|Json(input)| input).unwrap_or(CreateGroupInput {
+        start: default_start(),
+        mkstream: default_mkstream(),
+    });
+
+    let mut args = vec!["CREATE".to_string(), stream, group, input.start];
+    if input.mkstream {
+        args.push("MKSTREAM".to_string());
+    }
+
+    let result =
+        match CommandService::execute(&app_state, &auth, Command::from_strings("XGROUP", args))
+            .await
+        {
+            Err(ApiError::RedisError(error)) if error.code() == Some("BUSYGROUP") => {
+                Ok(RedisValue::Okay)
+            }
+            result => result,
+        };
+
+    ResponseBuilder::new(encoding.into_inner()).respond(result, redis_value_to_json)
+}
+
+/// Adds a consumer to a group ahead of its first read.
+pub async fn join_group_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    encoding: ExtractEncoding,
+    Path((stream, group, consumer)): Path<(String, String, String)>,
+) -> Response {
+    let args = vec!["CREATECONSUMER".to_string(), stream, group, consumer];
+
+    let result =
+        CommandService::execute(&app_state, &auth, Command::from_strings("XGROUP", args)).await;
+
+    ResponseBuilder::new(encoding.into_inner()).respond(result, redis_value_to_json)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReadParams {
+    /// Maximum number of entries returned at once
+    #[serde(default = "default_count")]
+    count: u64,
+    /// Milliseconds to wait for new entries, capped by the maximum block time
+    #[serde(default = "default_block")]
+    block: u64,
+    /// Whether entries are acknowledged as they are delivered
+    #[serde(default)]
+    noack: bool,
+}
+
+fn default_count() -> u64 {
+    10
+}
+
+fn default_block() -> u64 {
+    5000
+}
+
+fn read_command(stream: &str, group: &str, consumer: &str, params: &ReadParams) -> Command {
+    let mut args = vec![
+        "GROUP".to_string(),
+        group.to_string(),
+        consumer.to_string(),
+        "COUNT".to_string(),
+        params.count.to_string(),
+        "BLOCK".to_string(),
+        params.block.to_string(),
+    ];
+
+    if params.noack {
+        args.push("NOACK".to_string());
+    }
+
+    args.extend(["STREAMS".to_string(), stream.to_string(), ">".to_string()]);
+
+    Command::from_strings("XREADGROUP", args)
+}
+
+/// Long-polls for entries not yet delivered to the group, responding with an empty list
+/// when none arrive within the block time.
+pub async fn read_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    encoding: ExtractEncoding,
+    Path((stream, group, consumer)): Path<(String, String, String)>,
+    Query(params): Query<ReadParams>,
+) -> Response {
+    let command = read_command(&stream, &group, &consumer, &params);
+
+    let result = CommandService::execute(&app_state, &auth, command).await;
+
+    ResponseBuilder::new(encoding.into_inner()).respond(result, stream_read_to_json)
+}
+
+/// Delivers new entries as server-sent events until the client disconnects or the server
+/// shuts down. Each entry is an `entry` event whose SSE id is the stream entry id; a failed
+/// read sends an `error` event and ends the stream.
+pub async fn events_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    encoding: ExtractEncoding,
+    Path((stream, group, consumer)): Path<(String, String, String)>,
+    Query(params): Query<ReadParams>,
+) -> Response {
+    let encoding = encoding.into_inner();
+    let shutdown = app_state.shutdown.clone();
+    let command = move || read_command(&stream, &group, &consumer, &params);
+
+    let events = stream::unfold(true, move |running| {
+        let app_state = app_state.clone();
+        let auth = auth.clone();
+        let shutdown = shutdown.clone();
+        let encoding = encoding.clone();
+        let command = command();
+
+        async move {
+            if !running || shutdown.is_triggered() {
+                return None;
+            }
+
+            let result = tokio::select! {
+                result = CommandService::execute(&app_state, &auth, command) => result,
+                _ = shutdown.wait() => return None,
+            };
+
+            match result {
+                Ok(reply) => {
+                    let events: Vec<Event> = match stream_read_to_json(reply, &encoding) {
+                        JsonValue::Array(entries) => entries
+                            .into_iter()
+                            .map(|entry| {
+                                let event = Event::default().event("entry");
+                                let event = match entry["id"].as_str() {
+                                    Some(id) => event.id(id),
+                                    None => event,
+                                };
+                                event.json_data(entry).unwrap_or_default()
+                            })
+                            .collect(),
+                        _ => vec![],
+                    };
+
+                    Some((events, true))
+                }
+                Err(error) => {
+                    let error = ResponseBuilder::new(encoding).build(Err(error));
+                    let event = Event::default()
+                        .event("error")
+                        .json_data(error)
+                        .unwrap_or_default();
+
+                    Some((vec![event], false))
+                }
+            }
+        }
+    })
+    .flat_map(|events| stream::iter(events.into_iter().map(Ok::<_, Infallible>)));
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AckInput {
+    ids: Vec<String>,
+}
+
+/// Acknowledges entries by id, responding with the number acknowledged.
+pub async fn ack_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    encoding: ExtractEncoding,
+    Path((stream, group)): Path<(String, String)>,
+    Json(input): Json<AckInput>,
+) -> Response {
+    if input.ids.is_empty() {
+        return ApiError::NoCommand.into_response();
+    }
+
+    let mut args = vec![stream, group];
+    args.extend(input.ids);
+
+    let result =
+        CommandService::execute(&app_state, &auth, Command::from_strings("XACK", args)).await;
+
+    ResponseBuilder::new(encoding.into_inner()).respond(result, redis_value_to_json)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClaimInput {
+    /// Milliseconds an entry must have been pending to be claimed
+    min_idle_time: u64,
+    /// ID to scan the pending entries from, the `next` of a previous claim
+    #[serde(default = "default_claim_start")]
+    start: String,
+    #[serde(default = "default_count")]
+    count: u64,
+}
+
+fn default_claim_start() -> String {
+    "0-0".to_string()
+}
+
+/// Converts an XAUTOCLAIM reply to `{"next", "entries", "deleted"}`.
+fn claim_to_json(reply: RedisValue, encoding: &str) -> JsonValue {
+    let mut parts = match reply {
+        RedisValue::Bulk(parts) => parts.into_iter(),
+        _ => vec![].into_iter(),
+    };
+
+    let next = parts
+        .next()
+        .map(|next| redis_value_to_json(next, ""))
+        .unwrap_or(JsonValue::Null);
+    let entries = stream_entries_to_json(parts.next().unwrap_or(RedisValue::Nil), encoding);
+    let deleted = parts
+        .next()
+        .map(|deleted| redis_value_to_json(deleted, ""))
+        .unwrap_or(JsonValue::Array(vec![]));
+
+    serde_json::json!({ "next": next, "entries": entries, "deleted": deleted })
+}
+
+/// Transfers entries pending for longer than `min_idle_time` to the consumer.
+pub async fn claim_handler(
+    Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
+    encoding: ExtractEncoding,
+    Path((stream, group, consumer)): Path<(String, String, String)>,
+    Json(input): Json<ClaimInput>,
+) -> Response {
+    let args = vec![
+        stream,
+        group,
+        consumer,
+        input.min_idle_time.to_string(),
+        input.start,
+        "COUNT".to_string(),
+        input.count.to_string(),
+    ];
+
+    let result =
+        CommandService::execute(&app_state, &auth, Command::from_strings("XAUTOCLAIM", args)).await;
+
+    ResponseBuilder::new(encoding.into_inner()).respond(result, claim_to_json)
+}
+
+pub fn stream_routes() -> Router {
+    Router::new()
+        .route("/streams/:stream/groups/:group", post(create_group_handler))
+        .route("/streams/:stream/groups/:group/ack", post(ack_handler))
+        .route(
+            "/streams/:stream/groups/:group/consumers/:consumer",
+            post(join_group_handler),
+        )
+        .route(
+            "/streams/:stream/groups/:group/consumers/:consumer/read",
+            post(read_handler),
+        )
+        .route(
+            "/streams/:stream/groups/:group/consumers/:consumer/claim",
+            post(claim_handler),
+        )
+        .route(
+            "/streams/:stream/groups/:group/consumers/:consumer/events",
+            get(events_handler),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use redis::Value;
+
+    use super::{claim_to_json, read_command, ReadParams};
+
+    #[test]
+    fn test_read_command() {
+        let params = ReadParams {
+            count: 5,
+            block: 1000,
+            noack: false,
+        };
+        let command = read_command("events", "workers", "worker-1", &params);
+
+        let args: Vec<String> = command
+            .args
+            .iter()
+            .map(|arg| arg.as_redis_string())
+            .collect();
+        assert_eq!(
+            args,
+            vec![
+                "GROUP", "workers", "worker-1", "COUNT", "5", "BLOCK", "1000", "STREAMS", "events",
+                ">"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_claim_to_json() {
+        let reply = Value::Bulk(vec![
+            Value::Data(b"0-0".to_vec()),
+            Value::Bulk(vec![Value::Bulk(vec![
+                Value::Data(b"1-0".to_vec()),
+                Value::Bulk(vec![
+                    Value::Data(b"job".to_vec()),
+                    Value::Data(b"a".to_vec()),
+                ]),
+            ])]),
+            Value::Bulk(vec![Value::Data(b"2-0".to_vec())]),
+        ]);
+
+        assert_eq!(
+            claim_to_json(reply, ""),
+            serde_json::json!({
+                "next": "0-0",
+                "entries": [{"id": "1-0", "fields": {"job": "a"}}],
+                "deleted": ["2-0"]
+            })
+        );
+    }
+}
@@ -1,21 +1,32 @@
 use std::sync::Arc;
 
-use axum::{response::IntoResponse, routing::post, Extension, Json, Router};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Extension, Json, Router,
+};
 
 use crate::{
     models::{
         api_input_data::ExtractEncoding, api_types::RedisResponse,
-        multi_api_input_data::MultiApiInput, response_builder::ResponseBuilder, Argument, Command,
+        multi_api_input_data::MultiApiInput, response_builder::ResponseBuilder, Argument,
+        AuthContext, Command,
     },
-    services::CommandService,
+    services::{command_service::PreparedCommand, CommandService},
     state::AppState,
 };
 
 pub async fn transaction_route_handler(
     Extension(app_state): Extension<Arc<AppState>>,
+    Extension(auth): Extension<AuthContext>,
     encoding: ExtractEncoding,
     payload: MultiApiInput,
-) -> impl IntoResponse {
+) -> Response {
+    if let Err(error) = app_state.limits.check_commands(payload.0.len()) {
+        return error.into_response();
+    }
+
     let mut command_list: Vec<Command> = vec![];
 
     for data in payload.0 {
@@ -25,7 +36,7 @@ pub async fn transaction_route_handler(
 
         let command_str = data[0].to_string().trim_matches('\"').to_string();
         if data.len() > 1 {
-            let arguments = data.iter().skip(1).map(|s| Argument::from(s)).collect();
+            let arguments = data.iter().skip(1).map(Argument::from).collect();
             let command = Command {
                 name: command_str,
                 args: arguments,
@@ -40,13 +51,54 @@ pub async fn transaction_route_handler(
         }
     }
 
-    let con = app_state.redis_pool.get().await.unwrap();
+    let prepared: Vec<PreparedCommand> = match command_list
+        .into_iter()
+        .map(|command| CommandService::prepare(&app_state, command))
+        .collect()
+    {
+        Ok(prepared) => prepared,
+        Err(error) => return error.into_response(),
+    };
+
+    if let Err(error) = app_state
+        .policy
+        .check_all(prepared.iter().map(|prepared| &prepared.command), &auth)
+    {
+        return error.into_response();
+    }
+
+    if let Err(error) = app_state
+        .rate_limiter
+        .check_commands(&auth, prepared.len(), &app_state.redis_pool)
+        .await
+    {
+        return error.into_response();
+    }
+
+    let con = match app_state.redis_pools.connection(&auth).await {
+        Ok(con) => con,
+        Err(error) => return error.into_response(),
+    };
+
+    let result: RedisResponse = CommandService::with_timeout(
+        app_state.command_timeout,
+        CommandService::process_transaction(prepared, con),
+    )
+    .await
+    .and_then(|result| result)
+    .and_then(|reply| {
+        app_state.limits.check_reply(&reply)?;
+        Ok(reply)
+    });
 
-    let result: RedisResponse = CommandService::process_transaction(command_list, con).await;
+    let status = match &result {
+        Err(error) => error.status_code(),
+        Ok(_) => StatusCode::OK,
+    };
 
     let response = ResponseBuilder::new(encoding.into_inner()).build_transaction(result);
 
-    return Json(response);
+    (status, Json(response)).into_response()
 }
 
 pub fn transaction_routes() -> Router {
@@ -0,0 +1,121 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::models::ApiError;
+
+/// Entries older than the lockout period are dropped once the table grows past this size.
+const MAX_ENTRIES: usize = 10_000;
+
+#[derive(Debug)]
+struct FailedAttempts {
+    count: u32,
+    first: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// Locks out client IPs after repeated failed authentication attempts.
+/// `max_failures` failures within `lockout` lock the IP out for `lockout`. Successful attempts
+/// do not reset the count, or a client holding one valid token could interleave it with guesses.
+pub struct AuthLockout {
+    max_failures: u32,
+    lockout: Duration,
+    attempts: Mutex<HashMap<IpAddr, FailedAttempts>>,
+}
+
+impl AuthLockout {
+    pub fn new(max_failures: u32, lockout: Duration) -> Self {
+        AuthLockout {
+            max_failures,
+            lockout,
+            attempts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Rejects the request if the IP is currently locked out.
+    pub fn check(&self, ip: IpAddr) -> Result<(), ApiError> {
+        let attempts = self.attempts.lock().unwrap();
+
+        match attempts.get(&ip).and_then(|attempt| attempt.locked_until) {
+            Some(locked_until) if locked_until > Instant::now() => Err(ApiError::LockedOut {
+                retry_after: (locked_until - Instant::now()).as_secs().max(1),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Records a failed attempt, locking the IP out once it reaches the limit.
+    pub fn record_failure(&self, ip: IpAddr) {
+        if self.max_failures == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let mut attempts = self.attempts.lock().unwrap();
+
+        if attempts.len() > MAX_ENTRIES {
+            attempts.retain(|_, attempt| now.duration_since(attempt.first) < self.lockout);
+        }
+
+        let attempt = attempts.entry(ip).or_insert(FailedAttempts {
+            count: 0,
+            first: now,
+            locked_until: None,
+        });
+
+        if now.duration_since(attempt.first) >= self.lockout {
+            *attempt = FailedAttempts {
+                count: 0,
+                first: now,
+                locked_until: None,
+            };
+        }
+
+        attempt.count += 1;
+
+        if attempt.count >= self.max_failures {
+            tracing::warn!(%ip, failures = attempt.count, "locking out client after failed authentication attempts");
+            attempt.locked_until = Some(now + self.lockout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        time::Duration,
+    };
+
+    use super::AuthLockout;
+
+    #[test]
+    fn test_lockout() {
+        let lockout = AuthLockout::new(3, Duration::from_secs(60));
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+        lockout.record_failure(ip);
+        lockout.record_failure(ip);
+        assert!(lockout.check(ip).is_ok());
+
+        lockout.record_failure(ip);
+        assert!(lockout.check(ip).is_err());
+        assert!(lockout
+            .check(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_failures_expire() {
+        let lockout = AuthLockout::new(2, Duration::from_millis(20));
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+        lockout.record_failure(ip);
+        std::thread::sleep(Duration::from_millis(30));
+        lockout.record_failure(ip);
+        assert!(lockout.check(ip).is_ok());
+    }
+}
@@ -0,0 +1,174 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    models::{api_types::JsonValue, command_table, Argument, Command},
+    services::CommandMetadata,
+};
+
+/// Extra time given to a blocking command over its block time before the request fails,
+/// covering the round trip to Redis.
+pub const BLOCKING_TIMEOUT_MARGIN: Duration = Duration::from_secs(2);
+
+/// Unit of the timeout argument of a blocking command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimeoutUnit {
+    Seconds,
+    Milliseconds,
+}
+
+/// Finds the timeout argument of a blocking command. `XREAD` and `XREADGROUP` only block
+/// with a `BLOCK` option.
+fn timeout_argument(command: &Command) -> Option<(usize, TimeoutUnit)> {
+    let last = command.args.len().checked_sub(1)?;
+
+    match command.name.to_uppercase().as_str() {
+        "BLPOP" | "BRPOP" | "BRPOPLPUSH" | "BLMOVE" | "BZPOPMIN" | "BZPOPMAX" => {
+            Some((last, TimeoutUnit::Seconds))
+        }
+        "BLMPOP" | "BZMPOP" => Some((0, TimeoutUnit::Seconds)),
+        "WAIT" => Some((1, TimeoutUnit::Milliseconds)),
+        "WAITAOF" => Some((2, TimeoutUnit::Milliseconds)),
+        "XREAD" | "XREADGROUP" => command
+            .args
+            .iter()
+            .take_while(|arg| !arg.as_redis_string().eq_ignore_ascii_case("STREAMS"))
+            .position(|arg| arg.as_redis_string().eq_ignore_ascii_case("BLOCK"))
+            .map(|position| (position + 1, TimeoutUnit::Milliseconds)),
+        _ => None,
+    }
+    .filter(|(index, _)| *index < command.args.len())
+}
+
+/// Recognizes commands that block a connection and caps how long they may block.
+#[derive(Clone)]
+pub struct BlockingCommands {
+    max_block_time: Duration,
+    metadata: Arc<CommandMetadata>,
+}
+
+impl BlockingCommands {
+    pub fn new(max_block_time: Duration, metadata: Arc<CommandMetadata>) -> Self {
+        BlockingCommands {
+            max_block_time,
+            metadata,
+        }
+    }
+
+    /// Returns how long the command may block, or `None` if it does not block. A timeout
+    /// of 0 (block forever) or above the maximum is rewritten to the maximum, so Redis
+    /// answers with its usual timeout reply instead of holding the connection.
+    /// Commands Redis reports as blocking that are missing from the built-in table, such as
+    /// module commands, run on the blocking pool too. Their timeout argument is unknown, so
+    /// they fail with a timeout if they block longer than the maximum.
+    pub fn limit(&self, command: &mut Command) -> Option<Duration> {
+        let Some((index, unit)) = timeout_argument(command) else {
+            let unknown = command_table::lookup(&command.name).is_none();
+            return (unknown && self.metadata.is_blocking(command)).then_some(self.max_block_time);
+        };
+
+        let requested = command.args[index]
+            .as_redis_string()
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite() && *value >= 0.0);
+
+        // an invalid timeout is left for Redis to reject
+        let Some(requested) = requested else {
+            return Some(self.max_block_time);
+        };
+
+        // a timeout too large for a Duration is capped like any other long timeout
+        let requested = match unit {
+            TimeoutUnit::Seconds => Duration::try_from_secs_f64(requested),
+            TimeoutUnit::Milliseconds => Duration::try_from_secs_f64(requested / 1000.0),
+        };
+
+        if let Some(requested) = requested
+            .ok()
+            .filter(|requested| !requested.is_zero() && *requested <= self.max_block_time)
+        {
+            return Some(requested);
+        }
+
+        let max = match unit {
+            TimeoutUnit::Seconds => format!("{:.3}", self.max_block_time.as_secs_f64()),
+            TimeoutUnit::Milliseconds => self.max_block_time.as_millis().to_string(),
+        };
+        command.args[index] = Argument(JsonValue::String(max));
+
+        Some(self.max_block_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::BlockingCommands;
+    use crate::models::{Argument, Command};
+    use crate::services::CommandMetadata;
+
+    fn command(parts: &[&str]) -> Command {
+        Command {
+            name: parts[0].to_string(),
+            args: parts[1..]
+                .iter()
+                .map(|part| Argument::from(&part.to_string()))
+                .collect(),
+        }
+    }
+
+    fn args(command: &Command) -> Vec<String> {
+        command
+            .args
+            .iter()
+            .map(|arg| arg.as_redis_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_limit_block_time() {
+        let blocking = BlockingCommands::new(
+            Duration::from_secs(30),
+            Arc::new(CommandMetadata::builtin()),
+        );
+
+        let mut blpop = command(&["BLPOP", "jobs", "5"]);
+        assert_eq!(blocking.limit(&mut blpop), Some(Duration::from_secs(5)));
+        assert_eq!(args(&blpop), vec!["jobs", "5"]);
+
+        let mut forever = command(&["blpop", "jobs", "other", "0"]);
+        assert_eq!(blocking.limit(&mut forever), Some(Duration::from_secs(30)));
+        assert_eq!(args(&forever), vec!["jobs", "other", "30.000"]);
+
+        let mut xread = command(&["XREAD", "BLOCK", "60000", "STREAMS", "events", "$"]);
+        assert_eq!(blocking.limit(&mut xread), Some(Duration::from_secs(30)));
+        assert_eq!(
+            args(&xread),
+            vec!["BLOCK", "30000", "STREAMS", "events", "$"]
+        );
+
+        let mut huge = command(&["BLPOP", "jobs", "1e30"]);
+        assert_eq!(blocking.limit(&mut huge), Some(Duration::from_secs(30)));
+        assert_eq!(args(&huge), vec!["jobs", "30.000"]);
+
+        let mut huge = command(&["XREAD", "BLOCK", "1e300", "STREAMS", "events", "$"]);
+        assert_eq!(blocking.limit(&mut huge), Some(Duration::from_secs(30)));
+        assert_eq!(
+            args(&huge),
+            vec!["BLOCK", "30000", "STREAMS", "events", "$"]
+        );
+
+        let mut blmpop = command(&["BLMPOP", "0.5", "1", "jobs", "LEFT"]);
+        assert_eq!(
+            blocking.limit(&mut blmpop),
+            Some(Duration::from_millis(500))
+        );
+
+        assert_eq!(blocking.limit(&mut command(&["GET", "jobs"])), None);
+        assert_eq!(
+            blocking.limit(&mut command(&["XREAD", "STREAMS", "block", "0"])),
+            None
+        );
+    }
+}
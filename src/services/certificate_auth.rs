@@ -0,0 +1,104 @@
+use std::fs;
+
+use crate::{
+    config::{ClientIdentityConfig, TlsConfig},
+    models::{AuthContext, ClientCertificate},
+};
+
+/// Maps the identity of verified client certificates to permissions.
+pub struct CertificateAuth {
+    identities: Vec<ClientIdentityConfig>,
+}
+
+impl CertificateAuth {
+    pub fn new(identities: Vec<ClientIdentityConfig>) -> Self {
+        CertificateAuth { identities }
+    }
+
+    /// Loads the identities file, `None` if client certificates are not used for authentication.
+    pub fn from_config(tls: Option<&TlsConfig>) -> Result<Option<Self>, String> {
+        let Some(path) = tls.and_then(|tls| tls.client_identities_path.as_ref()) else {
+            return Ok(None);
+        };
+
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+
+        let identities = serde_json::from_str(&content)
+            .map_err(|e| format!("invalid client identities file {}: {}", path, e))?;
+
+        Ok(Some(Self::new(identities)))
+    }
+
+    /// Returns the permissions of the first configured identity the certificate matches.
+    pub fn authenticate(&self, certificate: &ClientCertificate) -> Option<AuthContext> {
+        let identity = self.identities.iter().find(|identity| {
+            certificate
+                .identities()
+                .any(|candidate| candidate == identity.identity)
+        })?;
+
+        let name = match &identity.name {
+            Some(name) => name.clone(),
+            None => format!("cert:{}", identity.identity),
+        };
+
+        Some(AuthContext {
+            elevated: identity.elevated,
+            allowed_commands: identity.commands.as_ref().map(|commands| {
+                commands
+                    .iter()
+                    .map(|command| command.to_uppercase())
+                    .collect()
+            }),
+            key_prefix: identity.key_prefix.clone(),
+            read_only: identity.read_only,
+            ..AuthContext::new(&name)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CertificateAuth;
+    use crate::models::ClientCertificate;
+
+    #[test]
+    fn test_identity_mapping() {
+        let auth = CertificateAuth::new(
+            serde_json::from_str(
+                r#"[
+                    {"identity": "spiffe://mesh/orders", "commands": ["get"], "key_prefix": "orders:"},
+                    {"identity": "billing", "name": "billing", "read_only": true}
+                ]"#,
+            )
+            .unwrap(),
+        );
+
+        let orders = ClientCertificate {
+            subject: "CN=orders-7f9c".to_string(),
+            common_name: Some("orders-7f9c".to_string()),
+            alt_names: vec!["spiffe://mesh/orders".to_string()],
+        };
+        let context = auth.authenticate(&orders).unwrap();
+        assert_eq!(context.name, "cert:spiffe://mesh/orders");
+        assert_eq!(context.allowed_commands, Some(vec!["GET".to_string()]));
+        assert_eq!(context.key_prefix, Some("orders:".to_string()));
+
+        let billing = ClientCertificate {
+            subject: "CN=billing, O=mesh".to_string(),
+            common_name: Some("billing".to_string()),
+            alt_names: vec![],
+        };
+        let context = auth.authenticate(&billing).unwrap();
+        assert_eq!(context.name, "billing");
+        assert!(context.read_only);
+
+        let unknown = ClientCertificate {
+            subject: "CN=unknown".to_string(),
+            common_name: Some("unknown".to_string()),
+            alt_names: vec![],
+        };
+        assert!(auth.authenticate(&unknown).is_none());
+    }
+}
@@ -0,0 +1,357 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use deadpool_redis::Pool;
+
+use crate::{
+    models::{
+        api_types::{JsonValue, RedisValue},
+        command_table::{CommandSpec, KeySpec, BUILTIN_COMMANDS},
+        Command,
+    },
+    utils::command_docs_to_json,
+};
+
+/// Where the command metadata in use comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataSource {
+    Builtin,
+    Redis,
+}
+
+/// What a command does, as the layers handling it need to know.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandAnnotation {
+    /// Name of the matching entry, e.g. `GET` or `OBJECT|ENCODING`
+    pub name: String,
+    /// Indices in `command.args` holding keys
+    pub key_indices: Vec<usize>,
+    pub write: bool,
+    pub blocking: bool,
+    /// False if the command takes keys at positions the metadata does not describe
+    pub keys_known: bool,
+}
+
+struct CommandTable {
+    source: MetadataSource,
+    commands: HashMap<String, Arc<CommandSpec>>,
+    /// `COMMAND DOCS` documents, only known from Redis 7 on
+    docs: HashMap<String, JsonValue>,
+}
+
+/// Flags of a built-in entry, named as Redis names them.
+fn builtin_flags(spec: &CommandSpec) -> Vec<String> {
+    let mut flags = vec![if spec.write { "write" } else { "readonly" }];
+
+    if spec.blocking {
+        flags.push("blocking");
+    }
+    if matches!(spec.keys, KeySpec::NumKeys { .. } | KeySpec::Streams) {
+        flags.push("movablekeys");
+    }
+
+    flags.into_iter().map(String::from).collect()
+}
+
+/// Key positions and flags of the commands the server knows, queried by the permission
+/// checks, blocking command routing and anything else that needs to tell commands apart.
+/// Starts with the built-in table, replaced by the server's `COMMAND` reply on `refresh`
+/// so that module commands and commands newer than the built-in table are known too.
+pub struct CommandMetadata {
+    table: RwLock<CommandTable>,
+}
+
+impl CommandMetadata {
+    pub fn builtin() -> Self {
+        let commands = BUILTIN_COMMANDS
+            .iter()
+            .map(|spec| {
+                let spec = CommandSpec {
+                    flags: builtin_flags(spec),
+                    ..spec.clone()
+                };
+                (spec.name.to_string(), Arc::new(spec))
+            })
+            .collect();
+
+        CommandMetadata {
+            table: RwLock::new(CommandTable {
+                source: MetadataSource::Builtin,
+                commands,
+                docs: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn source(&self) -> MetadataSource {
+        self.table.read().unwrap().source
+    }
+
+    /// Returns the number of known commands, subcommands included.
+    pub fn len(&self) -> usize {
+        self.table.read().unwrap().commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Looks up a command or `CONTAINER|SUBCOMMAND` entry, ignoring case.
+    pub fn lookup(&self, name: &str) -> Option<Arc<CommandSpec>> {
+        self.table
+            .read()
+            .unwrap()
+            .commands
+            .get(&name.to_uppercase())
+            .cloned()
+    }
+
+    /// Returns every known command and subcommand, sorted by name.
+    pub fn commands(&self) -> Vec<Arc<CommandSpec>> {
+        let mut commands: Vec<_> = self
+            .table
+            .read()
+            .unwrap()
+            .commands
+            .values()
+            .cloned()
+            .collect();
+        commands.sort_by(|a, b| a.name.cmp(&b.name));
+        commands
+    }
+
+    /// Returns the `COMMAND DOCS` document of a command or `CONTAINER|SUBCOMMAND` entry.
+    pub fn docs(&self, name: &str) -> Option<JsonValue> {
+        self.table
+            .read()
+            .unwrap()
+            .docs
+            .get(&name.to_uppercase())
+            .cloned()
+    }
+
+    /// Finds the entry describing a command, preferring the entry of its subcommand
+    /// (e.g. `CONFIG|SET`) when there is one.
+    pub fn spec(&self, command: &Command) -> Option<Arc<CommandSpec>> {
+        let subcommand = command
+            .args
+            .first()
+            .map(|arg| format!("{}|{}", command.name, arg.as_redis_string()))
+            .and_then(|name| self.lookup(&name));
+
+        subcommand.or_else(|| self.lookup(&command.name))
+    }
+
+    /// Returns the key positions and flags of a command, or `None` for an unknown command.
+    pub fn annotate(&self, command: &Command) -> Option<CommandAnnotation> {
+        let spec = self.spec(command)?;
+
+        Some(CommandAnnotation {
+            name: spec.name.to_string(),
+            key_indices: spec.key_indices(command),
+            write: spec.write,
+            blocking: spec.blocking,
+            keys_known: spec.keys_known(),
+        })
+    }
+
+    /// Returns the keys of a command that only reads keys at known positions, the commands
+    /// whose replies may be cached until one of their keys changes.
+    pub fn read_keys(&self, command: &Command) -> Option<Vec<String>> {
+        let annotation = self.annotate(command)?;
+
+        if annotation.write
+            || annotation.blocking
+            || !annotation.keys_known
+            || annotation.key_indices.is_empty()
+        {
+            return None;
+        }
+
+        Some(
+            annotation
+                .key_indices
+                .iter()
+                .map(|index| command.args[*index].as_redis_string())
+                .collect(),
+        )
+    }
+
+    pub fn is_blocking(&self, command: &Command) -> bool {
+        self.spec(command).is_some_and(|spec| spec.blocking)
+    }
+
+    /// Parses a `COMMAND` reply, including the subcommands of container commands.
+    pub fn parse_command_reply(reply: &RedisValue) -> Result<Vec<CommandSpec>, String> {
+        let RedisValue::Bulk(entries) = reply else {
+            return Err("expected an array of commands".to_string());
+        };
+
+        let mut commands = vec![];
+
+        for entry in entries {
+            // servers older than 2.8.13 answer nil for unknown commands of COMMAND INFO
+            if *entry == RedisValue::Nil {
+                continue;
+            }
+
+            let spec = CommandSpec::from_command_info(entry)
+                .ok_or_else(|| "unexpected command entry".to_string())?;
+            commands.push(spec);
+
+            if let RedisValue::Bulk(fields) = entry {
+                if let Some(RedisValue::Bulk(subcommands)) = fields.get(9) {
+                    for subcommand in subcommands {
+                        commands.push(
+                            CommandSpec::from_command_info(subcommand)
+                                .ok_or_else(|| "unexpected subcommand entry".to_string())?,
+                        );
+                    }
+                }
+            }
+        }
+
+        if commands.is_empty() {
+            return Err("the reply holds no commands".to_string());
+        }
+
+        Ok(commands)
+    }
+
+    /// Replaces the metadata with the commands of the Redis server, returning how many
+    /// there are. The metadata in use is kept if the server cannot describe its commands.
+    /// Documents are left out on servers without `COMMAND DOCS`, before Redis 7.
+    pub async fn refresh(&self, pool: &Pool) -> Result<usize, String> {
+        let mut con = pool.get().await.map_err(|e| e.to_string())?;
+
+        let reply: RedisValue = redis::cmd("COMMAND")
+            .query_async(&mut con)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let docs = redis::cmd("COMMAND")
+            .arg("DOCS")
+            .query_async(&mut con)
+            .await
+            .map(command_docs_to_json)
+            .unwrap_or_default();
+
+        let commands: HashMap<String, Arc<CommandSpec>> = Self::parse_command_reply(&reply)?
+            .into_iter()
+            .map(|spec| (spec.name.to_string(), Arc::new(spec)))
+            .collect();
+        let count = commands.len();
+
+        *self.table.write().unwrap() = CommandTable {
+            source: MetadataSource::Redis,
+            commands,
+            docs,
+        };
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommandMetadata, MetadataSource};
+    use crate::models::{api_types::RedisValue, Argument, Command};
+
+    fn command(name: &str, args: &[&str]) -> Command {
+        Command {
+            name: name.to_string(),
+            args: args
+                .iter()
+                .map(|arg| Argument::from(&arg.to_string()))
+                .collect(),
+        }
+    }
+
+    fn info(name: &str, arity: i64, flags: &[&str], keys: (i64, i64, i64)) -> Vec<RedisValue> {
+        vec![
+            RedisValue::Data(name.as_bytes().to_vec()),
+            RedisValue::Int(arity),
+            RedisValue::Bulk(
+                flags
+                    .iter()
+                    .map(|flag| RedisValue::Status(flag.to_string()))
+                    .collect(),
+            ),
+            RedisValue::Int(keys.0),
+            RedisValue::Int(keys.1),
+            RedisValue::Int(keys.2),
+        ]
+    }
+
+    #[test]
+    fn test_builtin_annotations() {
+        let metadata = CommandMetadata::builtin();
+        assert_eq!(metadata.source(), MetadataSource::Builtin);
+
+        let blpop = metadata
+            .annotate(&command("blpop", &["a", "b", "0"]))
+            .unwrap();
+        assert_eq!(blpop.key_indices, vec![0, 1]);
+        assert!(blpop.write && blpop.blocking && blpop.keys_known);
+
+        assert!(!metadata.is_blocking(&command("get", &["a"])));
+        assert!(metadata.annotate(&command("nosuchcommand", &[])).is_none());
+    }
+
+    #[test]
+    fn test_parse_command_reply() {
+        let mut object = info("object", -2, &[], (0, 0, 0));
+        object.extend([
+            RedisValue::Bulk(vec![]),
+            RedisValue::Bulk(vec![]),
+            RedisValue::Bulk(vec![]),
+            RedisValue::Bulk(vec![RedisValue::Bulk(info(
+                "object|encoding",
+                3,
+                &["readonly"],
+                (2, 2, 1),
+            ))]),
+        ]);
+
+        let reply = RedisValue::Bulk(vec![
+            RedisValue::Bulk(info("get", 2, &["readonly", "fast"], (1, 1, 1))),
+            RedisValue::Bulk(info(
+                "zunionstore",
+                -4,
+                &["write", "movablekeys"],
+                (1, 1, 1),
+            )),
+            RedisValue::Bulk(info(
+                "mod.wait",
+                -2,
+                &["blocking", "movablekeys"],
+                (0, 0, 0),
+            )),
+            RedisValue::Bulk(info("flushall", -1, &[], (0, 0, 0))),
+            RedisValue::Bulk(object),
+        ]);
+
+        let commands = CommandMetadata::parse_command_reply(&reply).unwrap();
+        let spec = |name: &str| commands.iter().find(|spec| spec.name == name).unwrap();
+
+        assert!(!spec("GET").write);
+        // movable keys come from the built-in table, or are unknown without an entry
+        assert_eq!(
+            spec("ZUNIONSTORE").key_indices(&command("zunionstore", &["d", "2", "a", "b"])),
+            vec![0, 2, 3]
+        );
+        assert!(spec("MOD.WAIT").blocking);
+        assert!(!spec("MOD.WAIT").keys_known());
+        // admin commands stay writing commands
+        assert!(spec("FLUSHALL").write);
+        assert_eq!(
+            spec("OBJECT|ENCODING").key_indices(&command("object", &["encoding", "k"])),
+            vec![1]
+        );
+
+        assert!(CommandMetadata::parse_command_reply(&RedisValue::Bulk(vec![])).is_err());
+        assert!(CommandMetadata::parse_command_reply(&RedisValue::Okay).is_err());
+    }
+}
@@ -0,0 +1,463 @@
+use std::sync::Arc;
+
+use crate::{
+    config::{AppConfig, DangerousCommandPolicy},
+    models::{api_types::JsonValue, ApiError, AuthContext, Command},
+    services::{CommandMetadata, FunctionLibraries, ScriptRegistry},
+};
+
+/// A dangerous command, optionally restricted to a single subcommand (e.g. `CONFIG SET`).
+#[derive(Debug, Clone)]
+struct CommandRule {
+    name: String,
+    subcommand: Option<String>,
+}
+
+impl CommandRule {
+    fn parse(rule: &str) -> Option<Self> {
+        let mut parts = rule.split_whitespace();
+        let name = parts.next()?.to_uppercase();
+        let subcommand = parts.next().map(|s| s.to_uppercase());
+
+        Some(CommandRule { name, subcommand })
+    }
+
+    fn matches(&self, command: &Command) -> bool {
+        if !command.name.eq_ignore_ascii_case(&self.name) {
+            return false;
+        }
+
+        match &self.subcommand {
+            None => true,
+            Some(subcommand) => match command.args.first() {
+                Some(arg) => match &arg.0 {
+                    JsonValue::String(value) => value.eq_ignore_ascii_case(subcommand),
+                    _ => false,
+                },
+                None => false,
+            },
+        }
+    }
+}
+
+/// Commands reaching keys other than their key arguments, refused to callers restricted to a
+/// key prefix: scripts and functions may access any key, the others act on the whole keyspace.
+const UNSCOPED_COMMANDS: [&str; 13] = [
+    "EVAL",
+    "EVAL_RO",
+    "EVALSHA",
+    "EVALSHA_RO",
+    "FCALL",
+    "FCALL_RO",
+    "SCAN",
+    "KEYS",
+    "RANDOMKEY",
+    "DBSIZE",
+    "FLUSHDB",
+    "FLUSHALL",
+    "SWAPDB",
+];
+
+/// Guards the command service against dangerous commands and enforces the caller's permissions.
+/// Every command of a request is checked before anything is sent to Redis.
+#[derive(Clone)]
+pub struct CommandPolicy {
+    mode: DangerousCommandPolicy,
+    rules: Vec<CommandRule>,
+    metadata: Arc<CommandMetadata>,
+    scripts: Arc<ScriptRegistry>,
+    functions: Arc<FunctionLibraries>,
+}
+
+impl CommandPolicy {
+    pub fn new(
+        mode: DangerousCommandPolicy,
+        commands: &[String],
+        metadata: Arc<CommandMetadata>,
+        scripts: Arc<ScriptRegistry>,
+        functions: Arc<FunctionLibraries>,
+    ) -> Self {
+        let rules = commands
+            .iter()
+            .filter_map(|command| CommandRule::parse(command))
+            .collect();
+
+        CommandPolicy {
+            mode,
+            rules,
+            metadata,
+            scripts,
+            functions,
+        }
+    }
+
+    pub fn from_config(
+        app_config: &AppConfig,
+        metadata: Arc<CommandMetadata>,
+        scripts: Arc<ScriptRegistry>,
+        functions: Arc<FunctionLibraries>,
+    ) -> Self {
+        Self::new(
+            app_config.dangerous_command_policy,
+            &app_config.dangerous_commands,
+            metadata,
+            scripts,
+            functions,
+        )
+    }
+
+    /// Returns true if the command matches one of the configured dangerous commands.
+    /// Calls of registered scripts and of functions from deployed libraries never are: their
+    /// source was vetted by the operator, or by a caller allowed to load it.
+    pub fn is_dangerous(&self, command: &Command) -> bool {
+        self.rules.iter().any(|rule| rule.matches(command)) && !self.is_vetted_call(command)
+    }
+
+    fn is_vetted_call(&self, command: &Command) -> bool {
+        let Some(target) = command.args.first().map(|arg| arg.as_redis_string()) else {
+            return false;
+        };
+
+        match command.name.to_uppercase().as_str() {
+            "EVALSHA" | "EVALSHA_RO" => self.scripts.contains_sha(&target),
+            "FCALL" | "FCALL_RO" => self.functions.contains_function(&target),
+            _ => false,
+        }
+    }
+
+    /// Checks whether the caller is allowed to run the command.
+    pub fn check(&self, command: &Command, auth: &AuthContext) -> Result<(), ApiError> {
+        self.check_permissions(command, auth)?;
+
+        if !self.is_dangerous(command) {
+            return Ok(());
+        }
+
+        match self.mode {
+            DangerousCommandPolicy::Allow => Ok(()),
+            DangerousCommandPolicy::Elevated if auth.elevated => Ok(()),
+            _ => Err(ApiError::CommandNotAllowed(command.name.to_uppercase())),
+        }
+    }
+
+    /// Checks the allowed commands, read-only flag and key prefix of the caller.
+    /// Commands missing from the command metadata are rejected for read-only and prefixed
+    /// callers since their keys and side effects are unknown, as are commands with keys at
+    /// unknown positions for prefixed callers.
+    fn check_permissions(&self, command: &Command, auth: &AuthContext) -> Result<(), ApiError> {
+        let not_allowed = || ApiError::CommandNotAllowed(command.name.to_uppercase());
+
+        if let Some(allowed_commands) = &auth.allowed_commands {
+            if !allowed_commands
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(&command.name))
+            {
+                return Err(not_allowed());
+            }
+        }
+
+        if !auth.read_only && auth.key_prefix.is_none() {
+            return Ok(());
+        }
+
+        let annotation = self.metadata.annotate(command).ok_or_else(not_allowed)?;
+
+        if auth.read_only && annotation.write {
+            return Err(not_allowed());
+        }
+
+        if let Some(prefix) = &auth.key_prefix {
+            let unscoped = UNSCOPED_COMMANDS
+                .iter()
+                .any(|unscoped| unscoped.eq_ignore_ascii_case(&command.name));
+
+            if unscoped || !annotation.keys_known {
+                return Err(not_allowed());
+            }
+
+            for index in annotation.key_indices {
+                let key = command.args[index].as_redis_string();
+                if !key.starts_with(prefix.as_str()) {
+                    return Err(ApiError::KeyNotAllowed(key));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks a list of commands, as sent to the pipeline and transaction routes.
+    /// The first rejected command rejects the whole list.
+    pub fn check_all<'a>(
+        &self,
+        commands: impl IntoIterator<Item = &'a Command>,
+        auth: &AuthContext,
+    ) -> Result<(), ApiError> {
+        commands
+            .into_iter()
+            .try_for_each(|command| self.check(command, auth))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::CommandPolicy;
+    use crate::config::{DangerousCommandPolicy, DEFAULT_DANGEROUS_COMMANDS, SCRIPTING_COMMANDS};
+    use crate::models::{Argument, AuthContext, Command};
+    use crate::services::{
+        script_registry::RegisteredScript, CommandMetadata, FunctionLibraries, ScriptRegistry,
+    };
+
+    fn command(name: &str, args: &[&str]) -> Command {
+        Command {
+            name: name.to_string(),
+            args: args
+                .iter()
+                .map(|arg| Argument::from(&arg.to_string()))
+                .collect(),
+        }
+    }
+
+    fn policy(mode: DangerousCommandPolicy) -> CommandPolicy {
+        let commands: Vec<String> = DEFAULT_DANGEROUS_COMMANDS
+            .iter()
+            .map(|command| command.to_string())
+            .collect();
+        CommandPolicy::new(
+            mode,
+            &commands,
+            Arc::new(CommandMetadata::builtin()),
+            Arc::new(ScriptRegistry::new()),
+            Arc::new(FunctionLibraries::new()),
+        )
+    }
+
+    #[test]
+    fn test_block_policy() {
+        let policy = policy(DangerousCommandPolicy::Block);
+        let elevated = AuthContext {
+            elevated: true,
+            ..Default::default()
+        };
+
+        assert!(policy.check(&command("flushall", &[]), &elevated).is_err());
+        assert!(policy.check(&command("get", &["key"]), &elevated).is_ok());
+    }
+
+    #[test]
+    fn test_elevated_policy() {
+        let policy = policy(DangerousCommandPolicy::Elevated);
+
+        assert!(policy
+            .check(&command("FLUSHDB", &[]), &AuthContext::default())
+            .is_err());
+        assert!(policy
+            .check(
+                &command("FLUSHDB", &[]),
+                &AuthContext {
+                    elevated: true,
+                    ..Default::default()
+                }
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_subcommand_rules() {
+        let policy = policy(DangerousCommandPolicy::Block);
+        let auth = AuthContext::default();
+
+        assert!(policy
+            .check(&command("config", &["set", "maxmemory", "1"]), &auth)
+            .is_err());
+        assert!(policy
+            .check(&command("config", &["get", "maxmemory"]), &auth)
+            .is_ok());
+        assert!(policy.check(&command("script", &["FLUSH"]), &auth).is_err());
+    }
+
+    #[test]
+    fn test_scripting_is_allowed_by_default() {
+        let policy = policy(DangerousCommandPolicy::Block);
+        let auth = AuthContext::default();
+
+        assert!(policy
+            .check(&command("EVAL", &["return 1", "0"]), &auth)
+            .is_ok());
+        assert!(policy
+            .check(&command("FCALL", &["enqueue", "0"]), &auth)
+            .is_ok());
+        assert!(policy
+            .check(&command("FUNCTION", &["FLUSH"]), &auth)
+            .is_err());
+        assert!(policy
+            .check(&command("function", &["delete", "jobs"]), &auth)
+            .is_err());
+    }
+
+    #[test]
+    fn test_scripts_are_dangerous() {
+        let scripts = Arc::new(ScriptRegistry::new());
+        let registered = scripts.insert(RegisteredScript::new("incr", "return 1"));
+        let functions = Arc::new(FunctionLibraries::new());
+        functions.record(["enqueue".to_string()]);
+        let commands: Vec<String> = DEFAULT_DANGEROUS_COMMANDS
+            .iter()
+            .chain(SCRIPTING_COMMANDS.iter())
+            .map(|command| command.to_string())
+            .collect();
+        let policy = CommandPolicy::new(
+            DangerousCommandPolicy::Elevated,
+            &commands,
+            Arc::new(CommandMetadata::builtin()),
+            scripts,
+            functions,
+        );
+        let auth = AuthContext::default();
+
+        let flushall = "return redis.call('FLUSHALL')";
+        assert!(policy
+            .check(&command("EVAL", &[flushall, "0"]), &auth)
+            .is_err());
+        assert!(policy
+            .check(&command("eval_ro", &[flushall, "0"]), &auth)
+            .is_err());
+        assert!(policy
+            .check(
+                &command("EVALSHA", &[&RegisteredScript::new("x", flushall).sha, "0"]),
+                &auth
+            )
+            .is_err());
+        assert!(policy
+            .check(&command("FCALL", &["flush", "0"]), &auth)
+            .is_err());
+        assert!(policy
+            .check(&command("SCRIPT", &["LOAD", flushall]), &auth)
+            .is_err());
+        assert!(policy
+            .check(&command("FUNCTION", &["LOAD", "#!lua name=x"]), &auth)
+            .is_err());
+
+        assert!(policy
+            .check(&command("EVALSHA", &[&registered.sha, "0"]), &auth)
+            .is_ok());
+        assert!(policy
+            .check(&command("FCALL_RO", &["enqueue", "0"]), &auth)
+            .is_ok());
+        assert!(policy
+            .check(
+                &command("EVAL", &[flushall, "0"]),
+                &AuthContext {
+                    elevated: true,
+                    ..Default::default()
+                }
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_permissions() {
+        let policy = policy(DangerousCommandPolicy::Allow);
+        let auth = AuthContext {
+            allowed_commands: Some(vec![
+                "GET".to_string(),
+                "SET".to_string(),
+                "MGET".to_string(),
+            ]),
+            key_prefix: Some("jobs:".to_string()),
+            ..Default::default()
+        };
+
+        assert!(policy.check(&command("get", &["jobs:1"]), &auth).is_ok());
+        assert!(policy.check(&command("get", &["users:1"]), &auth).is_err());
+        assert!(policy
+            .check(&command("mget", &["jobs:1", "users:1"]), &auth)
+            .is_err());
+        assert!(policy.check(&command("del", &["jobs:1"]), &auth).is_err());
+
+        // keys given as options cannot be checked against the prefix
+        let auth = AuthContext {
+            key_prefix: Some("jobs:".to_string()),
+            ..Default::default()
+        };
+
+        assert!(policy
+            .check(&command("sort", &["jobs:list", "STORE", "users:1"]), &auth)
+            .is_err());
+        assert!(policy
+            .check(&command("sort_ro", &["jobs:list", "GET", "users:*"]), &auth)
+            .is_err());
+        assert!(policy
+            .check(
+                &command(
+                    "georadius",
+                    &["jobs:geo", "0", "0", "1", "km", "STORE", "users:1"]
+                ),
+                &auth
+            )
+            .is_err());
+        assert!(policy
+            .check(
+                &command("georadius_ro", &["jobs:geo", "0", "0", "1", "km"]),
+                &auth
+            )
+            .is_ok());
+
+        // scripts may access any key, whatever keys they declare
+        let flush = "return redis.call('DEL', 'users:1')";
+        assert!(policy
+            .check(&command("eval", &[flush, "0"]), &auth)
+            .is_err());
+        assert!(policy
+            .check(&command("EVAL_RO", &[flush, "1", "jobs:1"]), &auth)
+            .is_err());
+        assert!(policy
+            .check(
+                &command(
+                    "evalsha",
+                    &["a42059b356c875f0717db19a51f6aaca9ae659ea", "0"]
+                ),
+                &auth
+            )
+            .is_err());
+        assert!(policy
+            .check(&command("FCALL", &["enqueue", "1", "jobs:1"]), &auth)
+            .is_err());
+        assert!(policy
+            .check(&command("fcall_ro", &["peek", "0"]), &auth)
+            .is_err());
+
+        // keyspace-wide commands are not bound to the prefix
+        assert!(policy.check(&command("scan", &["0"]), &auth).is_err());
+        assert!(policy.check(&command("RANDOMKEY", &[]), &auth).is_err());
+        assert!(policy.check(&command("dbsize", &[]), &auth).is_err());
+        assert!(policy.check(&command("keys", &["jobs:*"]), &auth).is_err());
+
+        let read_only = AuthContext {
+            read_only: true,
+            ..Default::default()
+        };
+
+        assert!(policy.check(&command("get", &["a"]), &read_only).is_ok());
+        assert!(policy
+            .check(&command("set", &["a", "b"]), &read_only)
+            .is_err());
+        assert!(policy.check(&command("unknown", &[]), &read_only).is_err());
+    }
+
+    #[test]
+    fn test_check_all_inside_multi() {
+        let policy = policy(DangerousCommandPolicy::Block);
+        let commands = vec![
+            command("multi", &[]),
+            command("flushall", &[]),
+            command("exec", &[]),
+        ];
+
+        assert!(policy
+            .check_all(&commands, &AuthContext::default())
+            .is_err());
+    }
+}
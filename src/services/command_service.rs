@@ -1,13 +1,141 @@
-use std::sync::Arc;
+use std::{future::Future, sync::Arc, time::Duration};
 
 use deadpool_redis::{Connection, Pool};
 use futures::future::join_all;
 
-use crate::models::{api_types::RedisResponse, ApiError, Command};
+use crate::{
+    models::{api_types::RedisResponse, ApiError, AuthContext, Command},
+    services::{blocking_commands::BLOCKING_TIMEOUT_MARGIN, script_registry::RegisteredScript},
+    state::AppState,
+};
+
+/// A command ready to be sent, with what decides how it runs.
+pub struct PreparedCommand {
+    pub command: Command,
+    /// Block time of a blocking command, see `BlockingCommands::limit`
+    pub block: Option<Duration>,
+    /// Registered script an `EVALNAME` call was rewritten for, loaded again if Redis lost it
+    pub script: Option<Arc<RegisteredScript>>,
+}
+
+/// Holds the connection of a blocking command. If the call does not complete, because the
+/// client disconnected or it timed out, the connection is removed from the pool and closed,
+/// which makes Redis abandon the command instead of popping a value nobody receives.
+struct BlockingConnection(Option<Connection>);
+
+impl Drop for BlockingConnection {
+    fn drop(&mut self) {
+        if let Some(con) = self.0.take() {
+            drop(Connection::take(con));
+        }
+    }
+}
 
 pub struct CommandService;
 
 impl CommandService {
+    /// Runs a call to Redis, failing with `ApiError::Timeout` if it does not finish in time.
+    /// The connection of a cancelled call fails its PING check and is not reused.
+    pub async fn with_timeout<T>(
+        timeout: Option<Duration>,
+        future: impl Future<Output = T>,
+    ) -> Result<T, ApiError> {
+        match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, future)
+                .await
+                .map_err(|_| ApiError::Timeout),
+            None => Ok(future.await),
+        }
+    }
+
+    /// Checks the argument count, resolves calls to registered scripts and caps the block time
+    /// of blocking commands. Permissions are checked on the prepared command, so script calls
+    /// are checked as EVALSHA.
+    pub fn prepare(
+        app_state: &AppState,
+        mut command: Command,
+    ) -> Result<PreparedCommand, ApiError> {
+        app_state.limits.check_arguments(&command)?;
+
+        let script = app_state.scripts.resolve(&mut command)?;
+        let block = app_state.blocking_commands.limit(&mut command);
+
+        Ok(PreparedCommand {
+            command,
+            block,
+            script,
+        })
+    }
+
+    /// Checks a single command against the caller's permissions and counts it against their limits.
+    pub async fn authorize(
+        app_state: &AppState,
+        auth: &AuthContext,
+        command: &Command,
+    ) -> Result<(), ApiError> {
+        app_state.policy.check(command, auth)?;
+
+        app_state
+            .rate_limiter
+            .check_commands(auth, 1, &app_state.redis_pool)
+            .await
+    }
+
+    /// Checks a single command against the caller's permissions and limits, then runs it as
+    /// the caller's Redis user. Blocking commands run on the blocking pool with a capped block time.
+    /// Replies of cached read commands come from `ResponseCache` when it is connected.
+    /// Replies above the reply size limit fail with `ApiError::ReplyTooLarge`.
+    pub async fn execute(
+        app_state: &AppState,
+        auth: &AuthContext,
+        command: Command,
+    ) -> RedisResponse {
+        let prepared = Self::prepare(app_state, command)?;
+
+        Self::authorize(app_state, auth, &prepared.command).await?;
+
+        // callers with their own Redis user read through their own ACL, never from the cache
+        if auth.redis_user.is_none() && prepared.block.is_none() && prepared.script.is_none() {
+            let cached = app_state
+                .cache
+                .get_or_fetch(&prepared.command, &app_state.commands);
+
+            if let Some(result) = Self::with_timeout(app_state.command_timeout, cached).await? {
+                let reply = result?;
+                app_state.limits.check_reply(&reply)?;
+                return Ok(reply);
+            }
+        }
+
+        // blocking commands wait on their own pool so they cannot starve other requests
+        let pools = match prepared.block {
+            Some(_) => &app_state.blocking_pools,
+            None => &app_state.redis_pools,
+        };
+
+        let con = pools.connection(auth).await?;
+
+        let timeout = match prepared.block {
+            Some(block) => Some(block + BLOCKING_TIMEOUT_MARGIN),
+            None => app_state.command_timeout,
+        };
+
+        let reply = Self::with_timeout(timeout, Self::run(prepared, con)).await??;
+
+        app_state.limits.check_reply(&reply)?;
+
+        Ok(reply)
+    }
+
+    /// Runs a prepared command on a connection from the pool matching its kind.
+    pub async fn run(prepared: PreparedCommand, con: Connection) -> RedisResponse {
+        match (prepared.block, prepared.script) {
+            (Some(_), _) => Self::process_blocking_command(prepared.command, con).await,
+            (None, Some(script)) => Self::process_script(prepared.command, &script, con).await,
+            (None, None) => Self::process_command(prepared.command, con).await,
+        }
+    }
+
     pub async fn process_command(command: Command, mut con: Connection) -> RedisResponse {
         let mut cmd = redis::cmd(command.as_ref());
 
@@ -15,37 +143,108 @@ impl CommandService {
             cmd.arg(arg);
         }
 
-        let result: RedisResponse = cmd
-            .query_async(&mut con)
+        cmd.query_async(&mut con)
             .await
-            .map_err(ApiError::RedisError);
+            .map_err(ApiError::RedisError)
+    }
+
+    /// Runs an EVALSHA call of a registered script, loading the script and retrying once if
+    /// Redis does not have it, e.g. after a restart or SCRIPT FLUSH.
+    pub async fn process_script(
+        command: Command,
+        script: &RegisteredScript,
+        mut con: Connection,
+    ) -> RedisResponse {
+        let mut cmd = redis::cmd(command.as_ref());
 
-        return result;
+        for arg in command.args.iter() {
+            cmd.arg(arg);
+        }
+
+        match cmd.query_async(&mut con).await {
+            Err(error) if error.kind() == redis::ErrorKind::NoScriptError => {
+                Self::load_script(script, &mut con).await?;
+
+                cmd.query_async(&mut con)
+                    .await
+                    .map_err(ApiError::RedisError)
+            }
+            result => result.map_err(ApiError::RedisError),
+        }
+    }
+
+    /// Loads a registered script into the Redis script cache.
+    pub async fn load_script(script: &RegisteredScript, con: &mut Connection) -> RedisResponse {
+        redis::cmd("SCRIPT")
+            .arg("LOAD")
+            .arg(&script.source)
+            .query_async(con)
+            .await
+            .map_err(ApiError::RedisError)
+    }
+
+    /// Runs a command that blocks the connection, see `BlockingConnection`.
+    pub async fn process_blocking_command(command: Command, con: Connection) -> RedisResponse {
+        let mut guard = BlockingConnection(Some(con));
+
+        let mut cmd = redis::cmd(command.as_ref());
+
+        for arg in command.args.iter() {
+            cmd.arg(arg);
+        }
+
+        let con = guard
+            .0
+            .as_mut()
+            .expect("connection is held until the call completes");
+        let result = cmd.query_async(con).await.map_err(ApiError::RedisError);
+
+        // completed, the connection can go back to the pool
+        drop(guard.0.take());
+
+        result
     }
 
+    /// Runs the commands concurrently. Blocking commands use connections of `blocking_pool`.
     pub async fn process_pipeline(
-        commands: Vec<Command>,
+        commands: Vec<PreparedCommand>,
         redis_pool: Arc<Pool>,
+        blocking_pool: Arc<Pool>,
     ) -> Vec<RedisResponse> {
         let futures: Vec<_> = commands
             .into_iter()
-            .map(|command| {
-                let pool = redis_pool.clone();
+            .map(|prepared| {
+                let pool = match prepared.block {
+                    Some(_) => blocking_pool.clone(),
+                    None => redis_pool.clone(),
+                };
                 async move {
-                    let con = pool.get().await.unwrap();
-
-                    let result = Self::process_command(command, con).await;
-                    return result;
+                    match pool.get().await {
+                        Ok(con) => Self::run(prepared, con).await,
+                        Err(error) => Err(ApiError::PoolError(error)),
+                    }
                 }
             })
             .collect();
 
-        let results: Vec<RedisResponse> = join_all(futures).await;
-
-        return results;
+        join_all(futures).await
     }
 
-    pub async fn process_transaction(commands: Vec<Command>, mut con: Connection) -> RedisResponse {
+    /// Runs the commands atomically. Scripts called in the transaction are loaded beforehand,
+    /// as a missing script would fail inside EXEC.
+    pub async fn process_transaction(
+        commands: Vec<PreparedCommand>,
+        mut con: Connection,
+    ) -> RedisResponse {
+        for script in commands
+            .iter()
+            .filter_map(|prepared| prepared.script.as_ref())
+        {
+            Self::load_script(script, &mut con).await?;
+        }
+
+        let commands = commands.into_iter().map(|prepared| prepared.command);
+
         let mut transaction_pipeline = redis::pipe();
         // make the transaction atomic for transactional integrity
         transaction_pipeline.atomic();
@@ -57,11 +256,68 @@ impl CommandService {
             }
         }
 
-        let result: RedisResponse = transaction_pipeline
+        transaction_pipeline
             .query_async(&mut con)
             .await
-            .map_err(ApiError::RedisError);
+            .map_err(ApiError::RedisError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use axum::http::StatusCode;
+
+    use super::CommandService;
+    use crate::{
+        config::{RedisPoolConfig, RedisTlsConfig},
+        models::{Argument, Command},
+        utils::redis_pool,
+    };
+
+    #[tokio::test]
+    async fn test_with_timeout() {
+        let slow = tokio::time::sleep(Duration::from_millis(200));
+        let error = CommandService::with_timeout(Some(Duration::from_millis(10)), slow)
+            .await
+            .unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::GATEWAY_TIMEOUT);
+
+        assert_eq!(
+            CommandService::with_timeout(None, async { 1 })
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_blocking_command_discards_connection() {
+        let connection_info = redis_pool::build_connection_info(
+            &std::env::var("REDIS_URL").unwrap_or("redis://127.0.0.1:6379".to_string()),
+            &RedisTlsConfig::default(),
+            None,
+            None,
+        )
+        .unwrap();
+        let pool = Arc::new(
+            redis_pool::create_pool(connection_info, &RedisPoolConfig::default()).unwrap(),
+        );
+
+        let con = pool.get().await.unwrap();
+        assert_eq!(pool.status().size, 1);
+
+        let command = Command {
+            name: "GET".to_string(),
+            args: vec![Argument::from(&"blocking-test".to_string())],
+        };
+
+        // dropping the call before it completes, as on a client disconnect
+        let mut call = Box::pin(CommandService::process_blocking_command(command, con));
+        assert!(futures::poll!(call.as_mut()).is_pending());
+        drop(call);
 
-        return result;
+        assert_eq!(pool.status().size, 0);
     }
 }
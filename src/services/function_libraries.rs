@@ -0,0 +1,172 @@
+use std::{collections::HashSet, fs, sync::RwLock};
+
+use deadpool_redis::Pool;
+
+use crate::{
+    models::api_types::{JsonValue, RedisValue},
+    utils::function_list_to_json,
+};
+
+/// Deploys Redis Functions libraries kept as files next to the server's configuration, and
+/// remembers the functions they register.
+#[derive(Debug, Default)]
+pub struct FunctionLibraries {
+    functions: RwLock<HashSet<String>>,
+}
+
+impl FunctionLibraries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a deployed library registers the function.
+    pub fn contains_function(&self, name: &str) -> bool {
+        self.functions.read().unwrap().contains(name)
+    }
+
+    pub(crate) fn record(&self, functions: impl IntoIterator<Item = String>) {
+        self.functions.write().unwrap().extend(functions);
+    }
+
+    /// Reads the `*.lua` files of the directory, sorted by file name, as `(path, source)`.
+    pub fn read_dir(dir: &str) -> Result<Vec<(String, String)>, String> {
+        let entries = fs::read_dir(dir).map_err(|e| format!("cannot read {}: {}", dir, e))?;
+
+        let mut paths = vec![];
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("cannot read {}: {}", dir, e))?
+                .path();
+
+            if path.extension().and_then(|extension| extension.to_str()) == Some("lua") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        paths
+            .into_iter()
+            .map(|path| {
+                let source = fs::read_to_string(&path)
+                    .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+                Ok((path.display().to_string(), source))
+            })
+            .collect()
+    }
+
+    /// Loads every library of the directory with `FUNCTION LOAD REPLACE`, returning their names.
+    /// The functions of the libraries are read back with `FUNCTION LIST`.
+    pub async fn deploy(&self, pool: &Pool, dir: &str) -> Result<Vec<String>, String> {
+        let libraries = Self::read_dir(dir)?;
+
+        let mut con = pool.get().await.map_err(|e| e.to_string())?;
+        let mut names = vec![];
+
+        for (path, source) in libraries {
+            let name: String = redis::cmd("FUNCTION")
+                .arg("LOAD")
+                .arg("REPLACE")
+                .arg(&source)
+                .query_async(&mut con)
+                .await
+                .map_err(|e| format!("cannot load {}: {}", path, e))?;
+
+            let libraries: RedisValue = redis::cmd("FUNCTION")
+                .arg("LIST")
+                .arg("LIBRARYNAME")
+                .arg(&name)
+                .query_async(&mut con)
+                .await
+                .map_err(|e| format!("cannot list the functions of {}: {}", name, e))?;
+
+            self.record(library_functions(libraries, &name));
+
+            names.push(name);
+        }
+
+        Ok(names)
+    }
+}
+
+/// Names of the functions a `FUNCTION LIST` reply holds for the library. The library name is
+/// matched as a pattern by Redis, so other libraries may be listed too.
+fn library_functions(reply: RedisValue, library: &str) -> Vec<String> {
+    let JsonValue::Array(libraries) = function_list_to_json(reply, "") else {
+        return vec![];
+    };
+
+    libraries
+        .iter()
+        .filter(|entry| entry["library_name"] == library)
+        .filter_map(|entry| entry["functions"].as_array())
+        .flatten()
+        .filter_map(|function| function["name"].as_str())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{library_functions, FunctionLibraries};
+    use crate::models::api_types::RedisValue;
+
+    fn data(value: &str) -> RedisValue {
+        RedisValue::Data(value.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_library_functions() {
+        let library = |name: &str, functions: &[&str]| {
+            RedisValue::Bulk(vec![
+                data("library_name"),
+                data(name),
+                data("engine"),
+                data("LUA"),
+                data("functions"),
+                RedisValue::Bulk(
+                    functions
+                        .iter()
+                        .map(|function| {
+                            RedisValue::Bulk(vec![
+                                data("name"),
+                                data(function),
+                                data("description"),
+                                RedisValue::Nil,
+                                data("flags"),
+                                RedisValue::Bulk(vec![]),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ])
+        };
+
+        let reply = RedisValue::Bulk(vec![
+            library("jobs", &["enqueue", "dequeue"]),
+            library("jobs2", &["other"]),
+        ]);
+
+        assert_eq!(library_functions(reply, "jobs"), vec!["enqueue", "dequeue"]);
+    }
+
+    #[test]
+    fn test_read_dir() {
+        let dir = std::env::temp_dir().join(format!("rediserve-functions-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b.lua"), "#!lua name=b\n").unwrap();
+        fs::write(dir.join("a.lua"), "#!lua name=a\n").unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let libraries = FunctionLibraries::read_dir(dir.to_str().unwrap()).unwrap();
+
+        let sources: Vec<&str> = libraries
+            .iter()
+            .map(|(_, source)| source.as_str())
+            .collect();
+        assert_eq!(sources, vec!["#!lua name=a\n", "#!lua name=b\n"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
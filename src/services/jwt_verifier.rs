@@ -0,0 +1,229 @@
+use std::{fs, str::FromStr};
+
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{AlgorithmParameters, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde::Deserialize;
+
+use crate::{config::JwtConfig, models::AuthContext};
+
+/// Claims read from a verified token and mapped to the caller's permissions.
+#[derive(Debug, Deserialize)]
+struct JwtClaims {
+    sub: Option<String>,
+    #[serde(default)]
+    commands: Option<Vec<String>>,
+    #[serde(default)]
+    key_prefix: Option<String>,
+    #[serde(default)]
+    read_only: bool,
+}
+
+/// A verification key together with the algorithm it is used with.
+struct JwtKey {
+    kid: Option<String>,
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+/// Verifies JWT bearer tokens against the configured secret, public key or JWKS file.
+pub struct JwtVerifier {
+    keys: Vec<JwtKey>,
+    audience: Vec<String>,
+}
+
+impl JwtVerifier {
+    /// Loads the keys described by the configuration, `None` if JWT authentication is disabled.
+    pub fn from_config(config: &JwtConfig) -> Result<Option<Self>, String> {
+        if !config.is_enabled() {
+            return Ok(None);
+        }
+
+        let mut keys = vec![];
+
+        if let Some(secret) = &config.secret {
+            keys.push(JwtKey {
+                kid: None,
+                algorithm: Algorithm::HS256,
+                key: DecodingKey::from_secret(secret.as_bytes()),
+            });
+        }
+
+        if let Some(path) = &config.public_key_path {
+            let pem = fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+            let algorithm = Algorithm::from_str(&config.public_key_algorithm)
+                .map_err(|_| format!("unknown JWT algorithm {}", config.public_key_algorithm))?;
+
+            let key = match algorithm {
+                Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => {
+                    DecodingKey::from_rsa_pem(&pem)
+                }
+                Algorithm::EdDSA => DecodingKey::from_ed_pem(&pem),
+                _ => return Err(format!("unsupported JWT algorithm {:?}", algorithm)),
+            }
+            .map_err(|e| format!("invalid public key {}: {}", path, e))?;
+
+            keys.push(JwtKey {
+                kid: None,
+                algorithm,
+                key,
+            });
+        }
+
+        if let Some(path) = &config.jwks_path {
+            let content =
+                fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+            let jwks: JwkSet = serde_json::from_str(&content)
+                .map_err(|e| format!("invalid JWKS file {}: {}", path, e))?;
+
+            for jwk in jwks.keys.iter() {
+                let algorithm = match jwk.common.key_algorithm {
+                    Some(key_algorithm) => Algorithm::from_str(&key_algorithm.to_string()).ok(),
+                    None => match jwk.algorithm {
+                        AlgorithmParameters::RSA(_) => Some(Algorithm::RS256),
+                        AlgorithmParameters::OctetKeyPair(_) => Some(Algorithm::EdDSA),
+                        AlgorithmParameters::EllipticCurve(_) => Some(Algorithm::ES256),
+                        AlgorithmParameters::OctetKey(_) => Some(Algorithm::HS256),
+                    },
+                };
+
+                // keys using algorithms we cannot verify are skipped
+                let (Some(algorithm), Ok(key)) = (algorithm, DecodingKey::from_jwk(jwk)) else {
+                    continue;
+                };
+
+                keys.push(JwtKey {
+                    kid: jwk.common.key_id.clone(),
+                    algorithm,
+                    key,
+                });
+            }
+        }
+
+        if keys.is_empty() {
+            return Err("no usable JWT verification key".to_string());
+        }
+
+        Ok(Some(JwtVerifier {
+            keys,
+            audience: config.audience.clone(),
+        }))
+    }
+
+    /// Verifies the signature and the exp, nbf and aud claims of a token,
+    /// returning the permissions it grants.
+    pub fn verify(&self, token: &str) -> Option<AuthContext> {
+        let header = decode_header(token).ok()?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.validate_nbf = true;
+        if self.audience.is_empty() {
+            validation.validate_aud = false;
+        } else {
+            validation.set_audience(&self.audience);
+        }
+
+        let claims = self
+            .keys
+            .iter()
+            .filter(|key| key.algorithm == header.alg)
+            .filter(|key| match (&key.kid, &header.kid) {
+                (Some(kid), Some(header_kid)) => kid == header_kid,
+                _ => true,
+            })
+            .find_map(|key| decode::<JwtClaims>(token, &key.key, &validation).ok())?
+            .claims;
+
+        let name = match claims.sub {
+            Some(sub) => format!("jwt:{}", sub),
+            None => "jwt".to_string(),
+        };
+
+        Some(AuthContext {
+            allowed_commands: claims.commands.map(|commands| {
+                commands
+                    .iter()
+                    .map(|command| command.to_uppercase())
+                    .collect()
+            }),
+            key_prefix: claims.key_prefix,
+            read_only: claims.read_only,
+            ..AuthContext::new(&name)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{encode, get_current_timestamp, EncodingKey, Header};
+    use serde_json::json;
+
+    use super::JwtVerifier;
+    use crate::config::JwtConfig;
+
+    fn verifier(audience: Vec<String>) -> JwtVerifier {
+        JwtVerifier::from_config(&JwtConfig {
+            secret: Some("secret".to_string()),
+            audience,
+            ..Default::default()
+        })
+        .unwrap()
+        .unwrap()
+    }
+
+    fn token(claims: serde_json::Value, secret: &str) -> String {
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_claims_mapping() {
+        let token = token(
+            json!({
+                "sub": "worker",
+                "exp": get_current_timestamp() + 60,
+                "commands": ["get", "set"],
+                "key_prefix": "jobs:",
+                "read_only": true,
+            }),
+            "secret",
+        );
+
+        let auth = verifier(vec![]).verify(&token).unwrap();
+
+        assert_eq!(auth.name, "jwt:worker");
+        assert_eq!(
+            auth.allowed_commands,
+            Some(vec!["GET".to_string(), "SET".to_string()])
+        );
+        assert_eq!(auth.key_prefix, Some("jobs:".to_string()));
+        assert!(auth.read_only);
+    }
+
+    #[test]
+    fn test_rejected_tokens() {
+        let verifier = verifier(vec!["rediserve".to_string()]);
+        let now = get_current_timestamp();
+
+        let expired = token(json!({"exp": now - 600, "aud": "rediserve"}), "secret");
+        let not_yet_valid = token(
+            json!({"exp": now + 600, "nbf": now + 300, "aud": "rediserve"}),
+            "secret",
+        );
+        let wrong_audience = token(json!({"exp": now + 600, "aud": "other"}), "secret");
+        let wrong_secret = token(json!({"exp": now + 600, "aud": "rediserve"}), "other");
+        let valid = token(json!({"exp": now + 600, "aud": "rediserve"}), "secret");
+
+        assert!(verifier.verify(&expired).is_none());
+        assert!(verifier.verify(&not_yet_valid).is_none());
+        assert!(verifier.verify(&wrong_audience).is_none());
+        assert!(verifier.verify(&wrong_secret).is_none());
+        assert!(verifier.verify(&valid).is_some());
+    }
+}
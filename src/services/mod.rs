@@ -1,5 +1,31 @@
 // Exports your services
 
+pub mod auth_lockout;
+pub mod blocking_commands;
+pub mod certificate_auth;
+pub mod command_metadata;
+pub mod command_policy;
 pub mod command_service;
+pub mod function_libraries;
+pub mod jwt_verifier;
+pub mod rate_limiter;
+pub mod redis_pools;
+pub mod request_limits;
+pub mod response_cache;
+pub mod script_registry;
+pub mod token_store;
 
+pub use auth_lockout::AuthLockout;
+pub use blocking_commands::BlockingCommands;
+pub use certificate_auth::CertificateAuth;
+pub use command_metadata::CommandMetadata;
+pub use command_policy::CommandPolicy;
 pub use command_service::CommandService;
+pub use function_libraries::FunctionLibraries;
+pub use jwt_verifier::JwtVerifier;
+pub use rate_limiter::RateLimiter;
+pub use redis_pools::RedisPools;
+pub use request_limits::RequestLimits;
+pub use response_cache::ResponseCache;
+pub use script_registry::ScriptRegistry;
+pub use token_store::TokenStore;
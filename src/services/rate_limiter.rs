@@ -0,0 +1,223 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    config::RateLimitConfig,
+    models::{
+        api_types::{RedisValue, SharedRedisPool},
+        ApiError, AuthContext,
+    },
+};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Buckets idle for longer than this are dropped once the table grows past `MAX_BUCKETS`.
+const IDLE_BUCKET_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_BUCKETS: usize = 10_000;
+
+/// A token bucket refilled at `rate` tokens per second, holding at most one second worth of tokens.
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64) -> Self {
+        TokenBucket {
+            tokens: rate,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Takes `amount` tokens from the bucket or returns the number of seconds to wait.
+    /// Amounts larger than the bucket only need a full bucket and leave it in debt.
+    fn take(&mut self, rate: f64, amount: f64) -> Result<(), u64> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+        self.updated = now;
+
+        let needed = amount.min(rate);
+        if self.tokens >= needed {
+            self.tokens -= amount;
+            Ok(())
+        } else {
+            let wait = ((needed - self.tokens) / rate).ceil() as u64;
+            Err(wait.max(1))
+        }
+    }
+}
+
+/// Per token and per client IP rate limiting, plus optional daily command quotas.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimiter {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn take(&self, key: String, rate: Option<u32>, amount: usize) -> Result<(), ApiError> {
+        let rate = match rate {
+            Some(rate) if rate > 0 => rate as f64,
+            _ => return Ok(()),
+        };
+
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() > MAX_BUCKETS {
+            buckets.retain(|_, bucket| bucket.updated.elapsed() < IDLE_BUCKET_TIMEOUT);
+        }
+
+        buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket::new(rate))
+            .take(rate, amount as f64)
+            .map_err(|retry_after| ApiError::RateLimited { retry_after })
+    }
+
+    /// Counts one request against the caller's token and IP limits.
+    pub fn check_request(&self, auth: &AuthContext) -> Result<(), ApiError> {
+        self.take(
+            format!("token-requests:{}", auth.name),
+            self.config.token_requests_per_second,
+            1,
+        )?;
+
+        match auth.client_ip {
+            Some(ip) => self.take(
+                format!("ip-requests:{}", ip),
+                self.config.ip_requests_per_second,
+                1,
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Counts `count` commands against the caller's token and IP limits and daily quota.
+    pub async fn check_commands(
+        &self,
+        auth: &AuthContext,
+        count: usize,
+        redis_pool: &SharedRedisPool,
+    ) -> Result<(), ApiError> {
+        self.take(
+            format!("token-commands:{}", auth.name),
+            self.config.token_commands_per_second,
+            count,
+        )?;
+
+        if let Some(ip) = auth.client_ip {
+            self.take(
+                format!("ip-commands:{}", ip),
+                self.config.ip_commands_per_second,
+                count,
+            )?;
+        }
+
+        match self.config.daily_command_quota {
+            Some(quota) => Self::check_quota(auth, count as u64, quota, redis_pool).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Increments the caller's counter for the current UTC day, stored in Redis so that
+    /// quotas are shared between instances and survive restarts.
+    async fn check_quota(
+        auth: &AuthContext,
+        count: u64,
+        quota: u64,
+        redis_pool: &SharedRedisPool,
+    ) -> Result<(), ApiError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let day = now / SECONDS_PER_DAY;
+        let key = format!("rediserve:quota:{}:{}", auth.name, day);
+
+        let mut con = redis_pool.get().await?;
+
+        let (used, _): (u64, RedisValue) = redis::pipe()
+            .atomic()
+            .incr(&key, count)
+            .expire(&key, (2 * SECONDS_PER_DAY) as i64)
+            .query_async(&mut con)
+            .await?;
+
+        if used <= quota {
+            return Ok(());
+        }
+
+        // rejected commands do not use up the quota
+        redis::cmd("DECRBY")
+            .arg(&key)
+            .arg(count)
+            .query_async::<_, RedisValue>(&mut con)
+            .await?;
+
+        Err(ApiError::QuotaExceeded {
+            retry_after: SECONDS_PER_DAY - now % SECONDS_PER_DAY,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::{RateLimiter, TokenBucket};
+    use crate::config::RateLimitConfig;
+    use crate::models::{ApiError, AuthContext};
+
+    #[test]
+    fn test_token_bucket() {
+        let mut bucket = TokenBucket::new(2.0);
+
+        assert!(bucket.take(2.0, 1.0).is_ok());
+        assert!(bucket.take(2.0, 1.0).is_ok());
+        assert_eq!(bucket.take(2.0, 1.0), Err(1));
+    }
+
+    #[test]
+    fn test_large_amount_needs_full_bucket() {
+        let mut bucket = TokenBucket::new(5.0);
+
+        assert!(bucket.take(5.0, 20.0).is_ok());
+        assert!(bucket.take(5.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_request_limits() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            ip_requests_per_second: Some(1),
+            ..Default::default()
+        });
+
+        let first = AuthContext {
+            client_ip: Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+            ..Default::default()
+        };
+        let second = AuthContext {
+            client_ip: Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))),
+            ..Default::default()
+        };
+
+        assert!(limiter.check_request(&first).is_ok());
+        assert!(matches!(
+            limiter.check_request(&first),
+            Err(ApiError::RateLimited { retry_after: 1 })
+        ));
+        assert!(limiter.check_request(&second).is_ok());
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+};
+
+use deadpool_redis::{Connection, PoolError};
+use redis::{ConnectionInfo, ErrorKind, RedisError};
+
+use crate::{
+    config::RedisPoolConfig,
+    models::{api_types::SharedRedisPool, ApiError, AuthContext, RedisUser},
+    utils::redis_pool,
+};
+
+/// The default connection pool plus one pool per Redis ACL user callers are mapped to,
+/// so Redis enforces the permissions of that user and records it in its ACL log.
+pub struct RedisPools {
+    default: SharedRedisPool,
+    connection_info: ConnectionInfo,
+    pool_config: RedisPoolConfig,
+    users: RwLock<HashMap<RedisUser, SharedRedisPool>>,
+}
+
+impl RedisPools {
+    pub fn new(
+        default: SharedRedisPool,
+        connection_info: ConnectionInfo,
+        pool_config: RedisPoolConfig,
+    ) -> Self {
+        RedisPools {
+            default,
+            connection_info,
+            pool_config,
+            users: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the pool whose connections authenticate as the caller's Redis user,
+    /// creating it on first use.
+    pub fn get(&self, auth: &AuthContext) -> Result<SharedRedisPool, ApiError> {
+        let Some(user) = &auth.redis_user else {
+            return Ok(self.default.clone());
+        };
+
+        if let Some(pool) = self.users.read().unwrap().get(user) {
+            return Ok(pool.clone());
+        }
+
+        let mut users = self.users.write().unwrap();
+        if let Some(pool) = users.get(user) {
+            return Ok(pool.clone());
+        }
+
+        let mut connection_info = self.connection_info.clone();
+        connection_info.redis.username = Some(user.username.clone());
+        connection_info.redis.password = user.password.clone();
+
+        tracing::info!(redis_user = %user.username, "creating connection pool for Redis user");
+
+        let pool = redis_pool::create_pool(connection_info, &self.pool_config).map_err(|error| {
+            tracing::error!(redis_user = %user.username, %error, "cannot create connection pool");
+            ApiError::PoolError(PoolError::Backend(RedisError::from((
+                ErrorKind::ClientError,
+                "cannot create connection pool",
+                error,
+            ))))
+        })?;
+
+        let pool = Arc::new(pool);
+        users.insert(user.clone(), pool.clone());
+
+        Ok(pool)
+    }
+
+    /// Takes a connection from the caller's pool.
+    pub async fn connection(&self, auth: &AuthContext) -> Result<Connection, ApiError> {
+        Ok(self.get(auth)?.get().await?)
+    }
+
+    /// Drops the pools of Redis users no token maps to anymore, e.g. after the tokens file
+    /// was reloaded with rotated credentials. Requests using a dropped pool finish normally.
+    pub fn retain_users(&self, users: &HashSet<RedisUser>) {
+        self.users
+            .write()
+            .unwrap()
+            .retain(|user, _| users.contains(user));
+    }
+
+    /// Closes every pool, dropping idle connections and failing requests still waiting for one.
+    pub fn close(&self) {
+        self.default.close();
+
+        for pool in self.users.read().unwrap().values() {
+            pool.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, sync::Arc};
+
+    use super::RedisPools;
+    use crate::{
+        config::{RedisPoolConfig, RedisTlsConfig},
+        models::{AuthContext, RedisUser},
+        utils::redis_pool,
+    };
+
+    #[test]
+    fn test_pool_per_user() {
+        let connection_info = redis_pool::build_connection_info(
+            "redis://127.0.0.1:6379",
+            &RedisTlsConfig::default(),
+            None,
+            None,
+        )
+        .unwrap();
+        let pool_config = RedisPoolConfig::default();
+        let default =
+            Arc::new(redis_pool::create_pool(connection_info.clone(), &pool_config).unwrap());
+        let pools = RedisPools::new(default.clone(), connection_info, pool_config);
+
+        let worker = AuthContext {
+            redis_user: Some(RedisUser {
+                username: "worker".to_string(),
+                password: Some("secret".to_string()),
+            }),
+            ..AuthContext::new("worker")
+        };
+
+        assert!(Arc::ptr_eq(
+            &pools.get(&AuthContext::new("default")).unwrap(),
+            &default
+        ));
+        let pool = pools.get(&worker).unwrap();
+        assert!(!Arc::ptr_eq(&pool, &default));
+        assert!(Arc::ptr_eq(&pool, &pools.get(&worker).unwrap()));
+
+        // a pool is kept while a token maps to its user, and recreated once evicted
+        pools.retain_users(&HashSet::from([worker.redis_user.clone().unwrap()]));
+        assert!(Arc::ptr_eq(&pool, &pools.get(&worker).unwrap()));
+        pools.retain_users(&HashSet::new());
+        assert!(!Arc::ptr_eq(&pool, &pools.get(&worker).unwrap()));
+    }
+}
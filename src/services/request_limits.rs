@@ -0,0 +1,132 @@
+use crate::{
+    config::RequestLimitsConfig,
+    models::{
+        api_types::{RedisResponse, RedisValue},
+        ApiError, Command,
+    },
+};
+
+/// Rejects requests and replies too large to handle safely: pipelines with too many
+/// commands, commands with too many arguments and replies too large to serialize.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestLimits {
+    config: RequestLimitsConfig,
+}
+
+impl RequestLimits {
+    pub fn new(config: RequestLimitsConfig) -> Self {
+        RequestLimits { config }
+    }
+
+    pub fn max_body_bytes(&self) -> Option<usize> {
+        self.config.max_body_bytes
+    }
+
+    /// Checks the number of commands of a pipeline or transaction.
+    pub fn check_commands(&self, count: usize) -> Result<(), ApiError> {
+        match self.config.max_pipeline_commands {
+            Some(max) if count > max => Err(ApiError::RequestTooLarge(format!(
+                "{} commands, at most {} are allowed per request",
+                count, max
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the number of arguments of a command.
+    pub fn check_arguments(&self, command: &Command) -> Result<(), ApiError> {
+        match self.config.max_command_arguments {
+            Some(max) if command.args.len() > max => Err(ApiError::RequestTooLarge(format!(
+                "{} has {} arguments, at most {} are allowed",
+                command.name.to_uppercase(),
+                command.args.len(),
+                max
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the size of a reply before it is converted to JSON.
+    pub fn check_reply(&self, reply: &RedisValue) -> Result<(), ApiError> {
+        self.check_reply_size(reply_size(reply))
+    }
+
+    /// Checks the total size of the replies of a pipeline.
+    pub fn check_replies(&self, replies: &[RedisResponse]) -> Result<(), ApiError> {
+        self.check_reply_size(replies.iter().flatten().map(reply_size).sum())
+    }
+
+    fn check_reply_size(&self, size: usize) -> Result<(), ApiError> {
+        match self.config.max_reply_bytes {
+            Some(limit) if size > limit => Err(ApiError::ReplyTooLarge { size, limit }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Estimates the memory a reply takes once serialized: the bytes of strings plus a few
+/// bytes per value for numbers and separators.
+pub fn reply_size(reply: &RedisValue) -> usize {
+    match reply {
+        RedisValue::Data(data) => data.len() + 2,
+        RedisValue::Status(status) => status.len() + 2,
+        RedisValue::Bulk(items) => items.iter().map(reply_size).sum::<usize>() + 2,
+        RedisValue::Int(_) => 8,
+        RedisValue::Nil | RedisValue::Okay => 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use super::{reply_size, RequestLimits};
+    use crate::{
+        config::RequestLimitsConfig,
+        models::{api_types::RedisValue, Argument, Command},
+    };
+
+    #[test]
+    fn test_limits() {
+        let limits = RequestLimits::new(RequestLimitsConfig {
+            max_body_bytes: None,
+            max_pipeline_commands: Some(2),
+            max_command_arguments: Some(1),
+            max_reply_bytes: Some(100),
+        });
+
+        assert!(limits.check_commands(2).is_ok());
+        assert_eq!(
+            limits.check_commands(3).unwrap_err().status_code(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+
+        let get = Command {
+            name: "get".to_string(),
+            args: vec![Argument::from(&"key".to_string())],
+        };
+        assert!(limits.check_arguments(&get).is_ok());
+        let mget = Command {
+            name: "mget".to_string(),
+            args: vec![
+                Argument::from(&"a".to_string()),
+                Argument::from(&"b".to_string()),
+            ],
+        };
+        assert!(limits.check_arguments(&mget).is_err());
+
+        let list = RedisValue::Bulk(vec![RedisValue::Data(vec![b'x'; 60]); 2]);
+        assert_eq!(reply_size(&list), 126);
+        assert_eq!(
+            limits.check_reply(&list).unwrap_err().status_code(),
+            StatusCode::INSUFFICIENT_STORAGE
+        );
+        assert!(limits
+            .check_replies(&[Ok(RedisValue::Okay), Ok(RedisValue::Int(1))])
+            .is_ok());
+
+        assert!(RequestLimits::new(RequestLimitsConfig::default())
+            .check_reply(&list)
+            .is_ok());
+    }
+}
@@ -0,0 +1,446 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use futures::StreamExt;
+use redis::{aio::MultiplexedConnection, Client, ConnectionInfo, Msg, RedisError};
+use tokio::sync::Notify;
+
+use crate::{
+    config::{CacheConfig, CachedCommandConfig},
+    models::{
+        api_types::{RedisResponse, RedisValue},
+        ApiError, Command,
+    },
+    services::{request_limits::reply_size, CommandMetadata},
+    shutdown::Shutdown,
+};
+
+/// Channel Redis publishes invalidation messages on in the `REDIRECT` tracking mode.
+const INVALIDATE_CHANNEL: &str = "__redis__:invalidate";
+
+/// Time waited before connecting again after the tracking connections were lost, doubled
+/// after each failed attempt up to `MAX_RECONNECT_DELAY`.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+struct CacheEntry {
+    value: RedisValue,
+    expires_at: Instant,
+    size: usize,
+    keys: Vec<String>,
+}
+
+/// Cached replies, keyed by command name and arguments, with the cache keys that depend on
+/// each Redis key.
+#[derive(Default)]
+struct CacheStore {
+    entries: HashMap<Vec<String>, CacheEntry>,
+    by_key: HashMap<String, HashSet<Vec<String>>>,
+    size: usize,
+}
+
+impl CacheStore {
+    fn get(&self, cache_key: &[String]) -> Option<RedisValue> {
+        self.entries
+            .get(cache_key)
+            .filter(|entry| entry.expires_at > Instant::now())
+            .map(|entry| entry.value.clone())
+    }
+
+    /// Adds an entry unless the cache is full once expired entries are dropped.
+    fn insert(&mut self, cache_key: Vec<String>, entry: CacheEntry, max_bytes: usize) -> bool {
+        self.remove(&cache_key);
+
+        if self.size + entry.size > max_bytes {
+            self.remove_expired();
+        }
+
+        if self.size + entry.size > max_bytes {
+            return false;
+        }
+
+        for key in &entry.keys {
+            self.by_key
+                .entry(key.clone())
+                .or_default()
+                .insert(cache_key.clone());
+        }
+        self.size += entry.size;
+        self.entries.insert(cache_key, entry);
+
+        true
+    }
+
+    fn remove(&mut self, cache_key: &[String]) {
+        let Some(entry) = self.entries.remove(cache_key) else {
+            return;
+        };
+
+        self.size -= entry.size;
+
+        for key in &entry.keys {
+            if let Some(cache_keys) = self.by_key.get_mut(key) {
+                cache_keys.remove(cache_key);
+                if cache_keys.is_empty() {
+                    self.by_key.remove(key);
+                }
+            }
+        }
+    }
+
+    fn remove_expired(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<Vec<String>> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.expires_at <= now)
+            .map(|(cache_key, _)| cache_key.clone())
+            .collect();
+
+        for cache_key in expired {
+            self.remove(&cache_key);
+        }
+    }
+
+    fn invalidate(&mut self, key: &str) {
+        for cache_key in self.by_key.remove(key).unwrap_or_default() {
+            self.remove(&cache_key);
+        }
+    }
+
+    fn clear(&mut self) {
+        *self = CacheStore::default();
+    }
+}
+
+/// In-process cache of the replies of configured read commands.
+///
+/// Misses are read through a dedicated connection with `CLIENT TRACKING` enabled, redirecting
+/// invalidation messages to a second connection subscribed to `__redis__:invalidate`. A key
+/// written by anyone evicts the replies that read it. While the tracking connections are down
+/// the cache is emptied and commands go to Redis as usual.
+pub struct ResponseCache {
+    commands: HashMap<String, CachedCommandConfig>,
+    max_bytes: usize,
+    connection_info: ConnectionInfo,
+    store: Mutex<CacheStore>,
+    /// Connection with tracking enabled, set while invalidation messages are received
+    tracked: RwLock<Option<MultiplexedConnection>>,
+    /// Counts invalidations, a miss is only stored if none arrived while it was read
+    invalidations: AtomicU64,
+    /// Signalled when the tracked connection fails, to set up both connections again
+    reconnect: Notify,
+}
+
+impl ResponseCache {
+    pub fn new(config: &CacheConfig, connection_info: ConnectionInfo) -> Self {
+        ResponseCache {
+            commands: config
+                .commands
+                .iter()
+                .map(|command| (command.name.clone(), command.clone()))
+                .collect(),
+            max_bytes: config.max_bytes,
+            connection_info,
+            store: Mutex::new(CacheStore::default()),
+            tracked: RwLock::new(None),
+            invalidations: AtomicU64::new(0),
+            reconnect: Notify::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.commands.is_empty()
+    }
+
+    /// Returns the cache key and the Redis keys of a command whose reply may be cached:
+    /// a configured command the metadata knows to be a read of known keys, see
+    /// `CommandMetadata::read_keys`.
+    fn cacheable(
+        &self,
+        command: &Command,
+        metadata: &CommandMetadata,
+    ) -> Option<(&CachedCommandConfig, Vec<String>, Vec<String>)> {
+        let config = self.commands.get(&command.name.to_uppercase())?;
+        let keys = metadata.read_keys(command)?;
+
+        let mut cache_key = vec![config.name.clone()];
+        cache_key.extend(command.args.iter().map(|arg| arg.as_redis_string()));
+
+        Some((config, cache_key, keys))
+    }
+
+    /// Answers a cacheable command from the cache, or reads it through the tracked connection
+    /// and caches the reply. Returns `None` for commands that are not cached and while the
+    /// cache is not connected, leaving them to the connection pool.
+    pub async fn get_or_fetch(
+        &self,
+        command: &Command,
+        metadata: &CommandMetadata,
+    ) -> Option<RedisResponse> {
+        let (config, cache_key, keys) = self.cacheable(command, metadata)?;
+
+        if let Some(value) = self.store.lock().unwrap().get(&cache_key) {
+            return Some(Ok(value));
+        }
+
+        let mut con = self.tracked.read().unwrap().clone()?;
+        let invalidations = self.invalidations.load(Ordering::SeqCst);
+
+        let mut cmd = redis::cmd(command.as_ref());
+        for arg in command.args.iter() {
+            cmd.arg(arg);
+        }
+
+        let value: RedisValue = match cmd.query_async(&mut con).await {
+            Ok(value) => value,
+            Err(error) => {
+                if is_connection_error(&error) {
+                    self.reconnect.notify_one();
+                }
+                return Some(Err(ApiError::RedisError(error)));
+            }
+        };
+
+        let size = reply_size(&value);
+
+        if size <= config.max_entry_bytes {
+            let mut store = self.store.lock().unwrap();
+
+            // checked under the lock, so an invalidation cannot slip in before the insert
+            if self.invalidations.load(Ordering::SeqCst) == invalidations {
+                let entry = CacheEntry {
+                    value: value.clone(),
+                    expires_at: Instant::now() + Duration::from_secs(config.ttl),
+                    size,
+                    keys,
+                };
+                store.insert(cache_key, entry, self.max_bytes);
+            }
+        }
+
+        Some(Ok(value))
+    }
+
+    /// Evicts the replies that read the keys, or every reply when Redis flushed its data.
+    fn invalidate(&self, keys: Option<Vec<String>>) {
+        let mut store = self.store.lock().unwrap();
+        self.invalidations.fetch_add(1, Ordering::SeqCst);
+
+        match keys {
+            Some(keys) => keys.iter().for_each(|key| store.invalidate(key)),
+            None => store.clear(),
+        }
+    }
+
+    /// Keeps the tracking connections up until the shutdown, emptying the cache whenever
+    /// they are lost.
+    pub fn watch(self: Arc<Self>, shutdown: Shutdown) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut delay = RECONNECT_DELAY;
+
+            loop {
+                let result = tokio::select! {
+                    result = self.track() => result,
+                    _ = shutdown.wait() => return,
+                };
+
+                *self.tracked.write().unwrap() = None;
+                self.invalidate(None);
+
+                match result {
+                    Ok(()) => {
+                        tracing::warn!("response cache disconnected from Redis");
+                        delay = RECONNECT_DELAY;
+                    }
+                    Err(error) => {
+                        tracing::warn!(%error, retry_in = ?delay, "response cache cannot track keys");
+                    }
+                }
+
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => delay = (delay * 2).min(MAX_RECONNECT_DELAY),
+                    _ = shutdown.wait() => return,
+                }
+            }
+        });
+    }
+
+    /// Sets up the tracking connections and applies invalidation messages until one of
+    /// the connections is lost.
+    async fn track(&self) -> Result<(), RedisError> {
+        let client = Client::open(self.connection_info.clone())?;
+
+        let mut con = client.get_async_connection().await?;
+        let id: i64 = redis::cmd("CLIENT").arg("ID").query_async(&mut con).await?;
+
+        let mut pubsub = con.into_pubsub();
+        pubsub.subscribe(INVALIDATE_CHANNEL).await?;
+
+        let mut tracked = client.get_multiplexed_tokio_connection().await?;
+        redis::cmd("CLIENT")
+            .arg("TRACKING")
+            .arg("ON")
+            .arg("REDIRECT")
+            .arg(id)
+            .query_async::<_, ()>(&mut tracked)
+            .await?;
+
+        *self.tracked.write().unwrap() = Some(tracked);
+        tracing::info!(
+            commands = self.commands.len(),
+            "response cache tracking keys"
+        );
+
+        let mut messages = pubsub.on_message();
+
+        loop {
+            tokio::select! {
+                message = messages.next() => match message {
+                    Some(message) => self.invalidate(invalidated_keys(&message)),
+                    None => return Ok(()),
+                },
+                _ = self.reconnect.notified() => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Keys of an invalidation message, `None` when all keys are invalidated after a flush.
+fn invalidated_keys(message: &Msg) -> Option<Vec<String>> {
+    message
+        .get_payload::<Option<Vec<Vec<u8>>>>()
+        .ok()
+        .flatten()
+        .map(|keys| {
+            keys.iter()
+                .map(|key| String::from_utf8_lossy(key).into_owned())
+                .collect()
+        })
+}
+
+fn is_connection_error(error: &RedisError) -> bool {
+    error.is_io_error() || error.is_connection_dropped() || error.is_connection_refusal()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{CacheEntry, CacheStore, ResponseCache};
+    use crate::{
+        config::{CacheConfig, CachedCommandConfig},
+        models::{api_types::RedisValue, Argument, Command},
+        services::CommandMetadata,
+    };
+
+    fn command(name: &str, args: &[&str]) -> Command {
+        Command {
+            name: name.to_string(),
+            args: args
+                .iter()
+                .map(|arg| Argument::from(&arg.to_string()))
+                .collect(),
+        }
+    }
+
+    fn entry(value: &str, keys: &[&str]) -> CacheEntry {
+        CacheEntry {
+            value: RedisValue::Data(value.as_bytes().to_vec()),
+            expires_at: Instant::now() + Duration::from_secs(60),
+            size: value.len(),
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+        }
+    }
+
+    fn cache_key(parts: &[&str]) -> Vec<String> {
+        parts.iter().map(|part| part.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_cached_command() {
+        assert_eq!(
+            CachedCommandConfig::parse("hgetall:10:1024", 60, 65536).unwrap(),
+            CachedCommandConfig {
+                name: "HGETALL".to_string(),
+                ttl: 10,
+                max_entry_bytes: 1024,
+            }
+        );
+        assert_eq!(
+            CachedCommandConfig::parse("GET", 60, 65536).unwrap().ttl,
+            60
+        );
+        assert!(CachedCommandConfig::parse("GET:0", 60, 65536).is_err());
+        assert!(CachedCommandConfig::parse(":10", 60, 65536).is_err());
+    }
+
+    #[test]
+    fn test_cacheable_commands() {
+        let cache = ResponseCache::new(
+            &CacheConfig {
+                commands: vec![
+                    CachedCommandConfig::parse("GET", 60, 65536).unwrap(),
+                    CachedCommandConfig::parse("SET", 60, 65536).unwrap(),
+                ],
+                max_bytes: 1024,
+            },
+            "redis://127.0.0.1:6379".parse().unwrap(),
+        );
+        let metadata = CommandMetadata::builtin();
+
+        let (_, cache_key, keys) = cache.cacheable(&command("get", &["a"]), &metadata).unwrap();
+        assert_eq!(cache_key, vec!["GET", "a"]);
+        assert_eq!(keys, vec!["a"]);
+
+        // writes and commands that are not configured are never cached
+        assert!(cache
+            .cacheable(&command("set", &["a", "1"]), &metadata)
+            .is_none());
+        assert!(cache
+            .cacheable(&command("hget", &["h", "f"]), &metadata)
+            .is_none());
+    }
+
+    #[test]
+    fn test_store_invalidation() {
+        let mut store = CacheStore::default();
+
+        assert!(store.insert(cache_key(&["HGET", "h", "a"]), entry("1", &["h"]), 100));
+        assert!(store.insert(cache_key(&["HGET", "h", "b"]), entry("2", &["h"]), 100));
+        assert!(store.insert(cache_key(&["MGET", "h", "k"]), entry("3", &["h", "k"]), 100));
+        assert!(store.insert(cache_key(&["GET", "k"]), entry("4", &["k"]), 100));
+        assert_eq!(store.size, 4);
+
+        store.invalidate("h");
+        assert!(store.get(&cache_key(&["HGET", "h", "a"])).is_none());
+        assert!(store.get(&cache_key(&["MGET", "h", "k"])).is_none());
+        assert!(store.get(&cache_key(&["GET", "k"])).is_some());
+        assert_eq!(store.size, 1);
+        assert_eq!(store.by_key["k"].len(), 1);
+
+        // full once the size limit is reached
+        assert!(!store.insert(
+            cache_key(&["GET", "big"]),
+            entry(&"x".repeat(100), &["big"]),
+            100
+        ));
+
+        let expired = CacheEntry {
+            expires_at: Instant::now(),
+            ..entry("5", &["old"])
+        };
+        assert!(store.insert(cache_key(&["GET", "old"]), expired, 100));
+        assert!(store.get(&cache_key(&["GET", "old"])).is_none());
+    }
+}
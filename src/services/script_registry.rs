@@ -0,0 +1,195 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    sync::{Arc, RwLock},
+};
+
+use crate::models::{api_types::JsonValue, ApiError, Argument, Command};
+
+/// Pseudo-command calling a registered script by name, with the arguments of EVAL:
+/// `EVALNAME name numkeys key [key ...] arg [arg ...]`.
+pub const EVALNAME: &str = "EVALNAME";
+
+/// A Lua script registered under a name
+#[derive(Debug)]
+pub struct RegisteredScript {
+    pub name: String,
+    pub source: String,
+    /// SHA1 digest Redis knows the script by
+    pub sha: String,
+}
+
+impl RegisteredScript {
+    pub fn new(name: &str, source: &str) -> Self {
+        RegisteredScript {
+            name: name.to_string(),
+            source: source.to_string(),
+            sha: redis::Script::new(source).get_hash().to_string(),
+        }
+    }
+}
+
+/// Named Lua scripts callers invoke without sending their source. Scripts are kept by this
+/// instance, so every instance behind a load balancer should preload the same directory.
+#[derive(Debug, Default)]
+pub struct ScriptRegistry {
+    scripts: RwLock<BTreeMap<String, Arc<RegisteredScript>>>,
+}
+
+impl ScriptRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers every `*.lua` file of the directory under its file name without the extension.
+    pub fn from_dir(dir: Option<&str>) -> Result<Self, String> {
+        let registry = Self::new();
+
+        let Some(dir) = dir else {
+            return Ok(registry);
+        };
+
+        let entries = fs::read_dir(dir).map_err(|e| format!("cannot read {}: {}", dir, e))?;
+
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("cannot read {}: {}", dir, e))?
+                .path();
+
+            if path.extension().and_then(|extension| extension.to_str()) != Some("lua") {
+                continue;
+            }
+
+            let name = path
+                .file_stem()
+                .and_then(|name| name.to_str())
+                .ok_or_else(|| format!("invalid script file name {}", path.display()))?;
+
+            validate_name(name)?;
+
+            let source = fs::read_to_string(&path)
+                .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+
+            registry.insert(RegisteredScript::new(name, &source));
+        }
+
+        Ok(registry)
+    }
+
+    pub fn insert(&self, script: RegisteredScript) -> Arc<RegisteredScript> {
+        let script = Arc::new(script);
+
+        self.scripts
+            .write()
+            .unwrap()
+            .insert(script.name.clone(), script.clone());
+
+        script
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<RegisteredScript>> {
+        self.scripts.read().unwrap().get(name).cloned()
+    }
+
+    pub fn remove(&self, name: &str) -> Option<Arc<RegisteredScript>> {
+        self.scripts.write().unwrap().remove(name)
+    }
+
+    /// Whether a registered script has this SHA1 digest.
+    pub fn contains_sha(&self, sha: &str) -> bool {
+        self.scripts
+            .read()
+            .unwrap()
+            .values()
+            .any(|script| script.sha.eq_ignore_ascii_case(sha))
+    }
+
+    pub fn list(&self) -> Vec<Arc<RegisteredScript>> {
+        self.scripts.read().unwrap().values().cloned().collect()
+    }
+
+    /// Rewrites an `EVALNAME` call to `EVALSHA` with the script's digest, returning the
+    /// script so it can be loaded again if Redis no longer has it. Other commands are
+    /// left unchanged.
+    pub fn resolve(
+        &self,
+        command: &mut Command,
+    ) -> Result<Option<Arc<RegisteredScript>>, ApiError> {
+        if !command.name.eq_ignore_ascii_case(EVALNAME) {
+            return Ok(None);
+        }
+
+        let name = command
+            .args
+            .first()
+            .map(|arg| arg.as_redis_string())
+            .unwrap_or_default();
+
+        let script = self.get(&name).ok_or(ApiError::UnknownScript(name))?;
+
+        command.name = "EVALSHA".to_string();
+        command.args[0] = Argument(JsonValue::String(script.sha.clone()));
+
+        Ok(Some(script))
+    }
+}
+
+/// Script names appear in URLs and file names, so they are limited to a safe set of characters.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 128
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid script name {:?}, use letters, digits, '_', '-' and '.'",
+            name
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RegisteredScript, ScriptRegistry};
+    use crate::models::{Argument, Command};
+
+    #[test]
+    fn test_resolve_evalname() {
+        let registry = ScriptRegistry::new();
+        let script = registry.insert(RegisteredScript::new(
+            "incr_by",
+            "return redis.call('INCRBY', KEYS[1], ARGV[1])",
+        ));
+        assert_eq!(script.sha.len(), 40);
+
+        let mut command = Command {
+            name: "evalname".to_string(),
+            args: ["incr_by", "1", "counter", "5"]
+                .iter()
+                .map(|arg| Argument::from(&arg.to_string()))
+                .collect(),
+        };
+
+        let resolved = registry.resolve(&mut command).unwrap().unwrap();
+        assert_eq!(resolved.name, "incr_by");
+        assert_eq!(command.name, "EVALSHA");
+        assert_eq!(command.args[0].as_redis_string(), script.sha);
+        assert_eq!(command.args[2].as_redis_string(), "counter");
+
+        let mut unknown = Command {
+            name: "EVALNAME".to_string(),
+            args: vec![Argument::from(&"missing".to_string())],
+        };
+        assert!(registry.resolve(&mut unknown).is_err());
+
+        let mut get = Command {
+            name: "GET".to_string(),
+            args: vec![],
+        };
+        assert!(registry.resolve(&mut get).unwrap().is_none());
+    }
+}
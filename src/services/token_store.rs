@@ -0,0 +1,287 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use subtle::ConstantTimeEq;
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::{
+    config::{AppConfig, TokenConfig},
+    models::{AuthContext, RedisUser},
+};
+
+/// Static tokens from the environment plus tokens from an optional JSON file,
+/// which can be reloaded while the server is running.
+pub struct TokenStore {
+    static_tokens: Vec<TokenConfig>,
+    file: Option<PathBuf>,
+    file_tokens: RwLock<Vec<TokenConfig>>,
+}
+
+impl TokenStore {
+    pub fn new(static_tokens: Vec<TokenConfig>, file: Option<PathBuf>) -> Result<Self, String> {
+        let file_tokens = match &file {
+            Some(path) => Self::read_file(path)?,
+            None => vec![],
+        };
+
+        Ok(TokenStore {
+            static_tokens,
+            file,
+            file_tokens: RwLock::new(file_tokens),
+        })
+    }
+
+    pub fn from_config(app_config: &AppConfig) -> Result<Self, String> {
+        Self::new(
+            app_config.tokens.clone(),
+            app_config.tokens_file.as_ref().map(PathBuf::from),
+        )
+    }
+
+    fn read_file(path: &Path) -> Result<Vec<TokenConfig>, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+
+        serde_json::from_str(&content)
+            .map_err(|e| format!("invalid tokens file {}: {}", path.display(), e))
+    }
+
+    /// Returns true if any token source is configured, even if it currently holds no token.
+    pub fn is_configured(&self) -> bool {
+        !self.static_tokens.is_empty() || self.file.is_some()
+    }
+
+    /// Re-reads the tokens file, keeping the current tokens if it cannot be loaded.
+    pub fn reload(&self) -> Result<usize, String> {
+        let Some(path) = &self.file else {
+            return Ok(0);
+        };
+
+        let tokens = Self::read_file(path)?;
+        let count = tokens.len();
+
+        *self.file_tokens.write().unwrap() = tokens;
+
+        Ok(count)
+    }
+
+    /// Finds the first provided token matching a configured, unexpired token.
+    pub fn authenticate(&self, provided_tokens: &[String]) -> Option<AuthContext> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let file_tokens = self.file_tokens.read().unwrap();
+
+        let token = self
+            .static_tokens
+            .iter()
+            .chain(file_tokens.iter())
+            .find(|token| {
+                let matches = provided_tokens
+                    .iter()
+                    .any(|provided| constant_time_eq(provided, &token.token));
+                let expired = token.expires_at.is_some_and(|expires_at| expires_at <= now);
+
+                if matches && expired {
+                    tracing::warn!(token = %token.name, "rejected expired token");
+                }
+
+                matches && !expired
+            })?;
+
+        Some(AuthContext {
+            elevated: token.elevated,
+            allowed_commands: token.commands.as_ref().map(|commands| {
+                commands
+                    .iter()
+                    .map(|command| command.to_uppercase())
+                    .collect()
+            }),
+            key_prefix: token.key_prefix.clone(),
+            read_only: token.read_only,
+            redis_user: redis_user(token),
+            ..AuthContext::new(&token.name)
+        })
+    }
+
+    fn reload_and_log(&self, reason: &str) {
+        match self.reload() {
+            Ok(count) => tracing::info!(reason, count, "reloaded tokens file"),
+            Err(error) => tracing::error!(reason, %error, "failed to reload tokens file"),
+        }
+    }
+
+    /// Redis users the configured tokens map callers to.
+    pub fn redis_users(&self) -> HashSet<RedisUser> {
+        let file_tokens = self.file_tokens.read().unwrap();
+
+        self.static_tokens
+            .iter()
+            .chain(file_tokens.iter())
+            .filter_map(redis_user)
+            .collect()
+    }
+
+    /// Reloads the tokens file on SIGHUP and whenever its modification time changes,
+    /// calling `on_reload` after each reload.
+    pub fn watch(
+        self: Arc<Self>,
+        interval: Duration,
+        on_reload: impl Fn(&TokenStore) + Send + Sync + 'static,
+    ) {
+        let Some(path) = self.file.clone() else {
+            return;
+        };
+
+        let on_reload: Arc<dyn Fn(&TokenStore) + Send + Sync> = Arc::new(on_reload);
+        let store = self.clone();
+        let store_on_reload = on_reload.clone();
+        tokio::spawn(async move {
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(hangup) => hangup,
+                Err(error) => {
+                    tracing::error!(%error, "cannot listen for SIGHUP");
+                    return;
+                }
+            };
+
+            while hangup.recv().await.is_some() {
+                store.reload_and_log("SIGHUP");
+                store_on_reload(&store);
+            }
+        });
+
+        tokio::spawn(async move {
+            let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+
+            let mut last_modified = modified(&path);
+            let mut ticker = tokio::time::interval(interval);
+
+            loop {
+                ticker.tick().await;
+
+                let current = modified(&path);
+                if current != last_modified {
+                    last_modified = current;
+                    self.reload_and_log("file change");
+                    on_reload(&self);
+                }
+            }
+        });
+    }
+}
+
+fn redis_user(token: &TokenConfig) -> Option<RedisUser> {
+    token.redis_username.as_ref().map(|username| RedisUser {
+        username: username.clone(),
+        password: token.redis_password.clone(),
+    })
+}
+
+/// Compares two secrets in time independent of where they differ.
+fn constant_time_eq(provided: &str, expected: &str) -> bool {
+    provided.as_bytes().ct_eq(expected.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        fs,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use super::TokenStore;
+    use crate::{config::TokenConfig, models::RedisUser};
+
+    #[test]
+    fn test_expired_token() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let store = TokenStore::new(
+            vec![
+                TokenConfig::parse(&format!("old:first:{}", now - 1)).unwrap(),
+                TokenConfig::parse(&format!("new:second:{}", now + 60)).unwrap(),
+            ],
+            None,
+        )
+        .unwrap();
+
+        assert!(store.authenticate(&["first".to_string()]).is_none());
+        assert_eq!(
+            store.authenticate(&["second".to_string()]).unwrap().name,
+            "new"
+        );
+
+        // an expired entry does not hide a later one sharing its secret
+        let store = TokenStore::new(
+            vec![
+                TokenConfig::parse(&format!("old:shared:{}", now - 1)).unwrap(),
+                TokenConfig::parse(&format!("rotated:shared:{}", now + 60)).unwrap(),
+            ],
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            store.authenticate(&["shared".to_string()]).unwrap().name,
+            "rotated"
+        );
+    }
+
+    #[test]
+    fn test_reload_file() {
+        let path =
+            std::env::temp_dir().join(format!("rediserve-tokens-{}.json", std::process::id()));
+        fs::write(&path, r#"[{"name": "ci", "token": "first"}]"#).unwrap();
+
+        let store = TokenStore::new(vec![], Some(path.clone())).unwrap();
+        assert!(store.authenticate(&["first".to_string()]).is_some());
+
+        fs::write(
+            &path,
+            r#"[{"name": "ci", "token": "second", "read_only": true}]"#,
+        )
+        .unwrap();
+        assert_eq!(store.reload(), Ok(1));
+
+        assert!(store.authenticate(&["first".to_string()]).is_none());
+        assert!(
+            store
+                .authenticate(&["second".to_string()])
+                .unwrap()
+                .read_only
+        );
+
+        // a broken file keeps the current tokens
+        fs::write(&path, "not json").unwrap();
+        assert!(store.reload().is_err());
+        assert!(store.authenticate(&["second".to_string()]).is_some());
+
+        fs::write(
+            &path,
+            r#"[{"name": "ci", "token": "third", "redis_username": "ci", "redis_password": "new"}]"#,
+        )
+        .unwrap();
+        assert_eq!(store.reload(), Ok(1));
+        assert_eq!(
+            store.redis_users(),
+            HashSet::from([RedisUser {
+                username: "ci".to_string(),
+                password: Some("new".to_string()),
+            }])
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,28 +1,152 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
-use deadpool_redis::{Config, Runtime};
-
-use crate::{config::AppConfig, models::api_types::SharedRedisPool};
+use crate::{
+    config::{AppConfig, CorsConfig, HttpCacheConfig},
+    models::api_types::SharedRedisPool,
+    services::{
+        AuthLockout, BlockingCommands, CertificateAuth, CommandMetadata, CommandPolicy,
+        FunctionLibraries, JwtVerifier, RateLimiter, RedisPools, RequestLimits, ResponseCache,
+        ScriptRegistry, TokenStore,
+    },
+    shutdown::Shutdown,
+    utils::redis_pool,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub redis_pool: SharedRedisPool,
-    pub token: Option<String>,
+    pub redis_pools: Arc<RedisPools>,
+    pub blocking_pools: Arc<RedisPools>,
+    pub blocking_commands: BlockingCommands,
+    pub commands: Arc<CommandMetadata>,
+    pub cache: Arc<ResponseCache>,
+    pub http_cache: HttpCacheConfig,
+    pub cors: CorsConfig,
+    pub tokens: Arc<TokenStore>,
+    pub allow_query_token: bool,
+    pub auth_lockout: Arc<AuthLockout>,
+    pub policy: CommandPolicy,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub limits: RequestLimits,
+    pub jwt_verifier: Option<Arc<JwtVerifier>>,
+    pub command_timeout: Option<Duration>,
+    pub certificate_auth: Option<Arc<CertificateAuth>>,
+    pub scripts: Arc<ScriptRegistry>,
+    pub functions: Arc<FunctionLibraries>,
+    pub shutdown: Shutdown,
 }
 
 impl AppState {
     pub fn new(app_config: &AppConfig) -> Self {
-        let cfg = Config::from_url(&app_config.redis_url);
+        let connection_info = match redis_pool::connection_info(app_config) {
+            Ok(connection_info) => connection_info,
+            Err(error) => {
+                eprintln!("Failed to create Redis pool: {}", error);
+                std::process::exit(1);
+            }
+        };
 
-        let pool = cfg
-            .create_pool(Some(Runtime::Tokio1))
-            .expect("Failed to create pool");
+        let pool = match redis_pool::create_pool(connection_info.clone(), &app_config.redis_pool) {
+            Ok(pool) => pool,
+            Err(error) => {
+                eprintln!("Failed to create Redis pool: {}", error);
+                std::process::exit(1);
+            }
+        };
 
         let shared_pool = Arc::new(pool);
 
+        let blocking_pool =
+            match redis_pool::create_pool(connection_info.clone(), &app_config.blocking_pool) {
+                Ok(pool) => Arc::new(pool),
+                Err(error) => {
+                    eprintln!("Failed to create Redis pool: {}", error);
+                    std::process::exit(1);
+                }
+            };
+
+        let tokens = match TokenStore::from_config(app_config) {
+            Ok(tokens) => Arc::new(tokens),
+            Err(error) => {
+                eprintln!("Failed to load tokens: {}", error);
+                std::process::exit(1);
+            }
+        };
+
+        let jwt_verifier = match JwtVerifier::from_config(&app_config.jwt) {
+            Ok(verifier) => verifier.map(Arc::new),
+            Err(error) => {
+                eprintln!("Failed to load JWT configuration: {}", error);
+                std::process::exit(1);
+            }
+        };
+
+        let certificate_auth = match CertificateAuth::from_config(app_config.tls.as_ref()) {
+            Ok(certificate_auth) => certificate_auth.map(Arc::new),
+            Err(error) => {
+                eprintln!("Failed to load client certificate identities: {}", error);
+                std::process::exit(1);
+            }
+        };
+
+        let scripts = match ScriptRegistry::from_dir(app_config.scripts_dir.as_deref()) {
+            Ok(scripts) => Arc::new(scripts),
+            Err(error) => {
+                eprintln!("Failed to load scripts: {}", error);
+                std::process::exit(1);
+            }
+        };
+
+        let functions = Arc::new(FunctionLibraries::new());
+        let commands = Arc::new(CommandMetadata::builtin());
+        let cache = Arc::new(ResponseCache::new(
+            &app_config.cache,
+            connection_info.clone(),
+        ));
+
         AppState {
-            redis_pool: shared_pool,
-            token: app_config.token.clone(),
+            redis_pool: shared_pool.clone(),
+            redis_pools: Arc::new(RedisPools::new(
+                shared_pool,
+                connection_info.clone(),
+                app_config.redis_pool.clone(),
+            )),
+            blocking_pools: Arc::new(RedisPools::new(
+                blocking_pool,
+                connection_info,
+                app_config.blocking_pool.clone(),
+            )),
+            blocking_commands: BlockingCommands::new(
+                Duration::from_millis(app_config.max_block_time),
+                commands.clone(),
+            ),
+            tokens,
+            allow_query_token: app_config.allow_query_token,
+            auth_lockout: Arc::new(AuthLockout::new(
+                app_config.auth_max_failures,
+                Duration::from_secs(app_config.auth_lockout_seconds),
+            )),
+            policy: CommandPolicy::from_config(
+                app_config,
+                commands.clone(),
+                scripts.clone(),
+                functions.clone(),
+            ),
+            rate_limiter: Arc::new(RateLimiter::new(app_config.rate_limit.clone())),
+            limits: RequestLimits::new(app_config.request_limits),
+            jwt_verifier,
+            command_timeout: app_config
+                .redis_pool
+                .command_timeout
+                .map(Duration::from_millis),
+            certificate_auth,
+            scripts,
+            functions,
+            commands,
+            cache,
+            http_cache: app_config.http_cache,
+            cors: app_config.cors.clone(),
+            shutdown: Shutdown::new(),
         }
     }
 }
@@ -0,0 +1,246 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader},
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    http::{header, HeaderMap, Uri},
+    response::Redirect,
+    Router,
+};
+use axum_server::{
+    accept::Accept,
+    tls_rustls::{RustlsAcceptor, RustlsConfig},
+};
+use futures::future::BoxFuture;
+use rustls::{
+    pki_types::{CertificateDer, PrivateKeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore, ServerConfig,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+};
+use tokio_rustls::server::TlsStream;
+use tower_http::add_extension::AddExtension;
+
+use crate::{config::TlsConfig, models::ClientCertificate, shutdown::Shutdown};
+
+fn read_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let file = File::open(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid certificate {}: {}", path, e))?;
+
+    if certs.is_empty() {
+        return Err(format!("no certificate found in {}", path));
+    }
+
+    Ok(certs)
+}
+
+fn read_key(path: &str) -> Result<PrivateKeyDer<'static>, String> {
+    let file = File::open(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .map_err(|e| format!("invalid private key {}: {}", path, e))?
+        .ok_or_else(|| format!("no private key found in {}", path))
+}
+
+/// Builds the rustls server configuration from the certificate and key files,
+/// verifying client certificates against the CA bundle when one is configured.
+pub fn load_server_config(tls: &TlsConfig) -> Result<ServerConfig, String> {
+    let certs = read_certs(&tls.cert_path)?;
+    let key = read_key(&tls.key_path)?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?;
+
+    let builder = match &tls.client_ca_path {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in read_certs(path)? {
+                roots
+                    .add(cert)
+                    .map_err(|e| format!("invalid CA certificate {}: {}", path, e))?;
+            }
+
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            let verifier = if tls.client_cert_required {
+                verifier
+            } else {
+                verifier.allow_unauthenticated()
+            };
+
+            builder.with_client_cert_verifier(
+                verifier
+                    .build()
+                    .map_err(|e| format!("invalid CA bundle {}: {}", path, e))?,
+            )
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut server_config = builder
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("invalid certificate or key: {}", e))?;
+
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(server_config)
+}
+
+/// Reloads the certificate, key and client CA bundle whenever one of the files changes on disk.
+/// Connections already established keep the previous certificate.
+pub fn watch(rustls_config: RustlsConfig, tls: TlsConfig) {
+    tokio::spawn(async move {
+        let modified = |path: &str| -> Option<SystemTime> {
+            fs::metadata(Path::new(path))
+                .and_then(|m| m.modified())
+                .ok()
+        };
+        let files_modified = || {
+            (
+                modified(&tls.cert_path),
+                modified(&tls.key_path),
+                tls.client_ca_path.as_deref().and_then(modified),
+            )
+        };
+
+        let mut last_modified = files_modified();
+        let mut ticker = tokio::time::interval(Duration::from_secs(tls.reload_interval.max(1)));
+
+        loop {
+            ticker.tick().await;
+
+            let current = files_modified();
+            if current == last_modified {
+                continue;
+            }
+            last_modified = current;
+
+            match load_server_config(&tls) {
+                Ok(server_config) => {
+                    rustls_config.reload_from_config(Arc::new(server_config));
+                    tracing::info!("reloaded TLS certificate");
+                }
+                Err(error) => tracing::error!(%error, "failed to reload TLS certificate"),
+            }
+        }
+    });
+}
+
+/// Completes the TLS handshake and attaches the verified client certificate, if any,
+/// to every request of the connection as an `Option<ClientCertificate>` extension.
+#[derive(Clone)]
+pub struct ClientCertAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl ClientCertAcceptor {
+    pub fn new(rustls_config: RustlsConfig) -> Self {
+        ClientCertAcceptor {
+            inner: RustlsAcceptor::new(rustls_config),
+        }
+    }
+}
+
+impl<I, S> Accept<I, S> for ClientCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = AddExtension<S, Option<ClientCertificate>>;
+    type Future = BoxFuture<'static, io::Result<(Self::Stream, Self::Service)>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let acceptor = self.inner.clone();
+
+        Box::pin(async move {
+            let (stream, service) = acceptor.accept(stream, service).await?;
+
+            let client_cert = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(|cert| ClientCertificate::from_der(cert));
+
+            Ok((stream, AddExtension::new(service, client_cert)))
+        })
+    }
+}
+
+/// Builds the `https://` location for a plain HTTP request.
+fn https_location(headers: &HeaderMap, uri: &Uri, https_port: u16) -> Option<String> {
+    let host = headers.get(header::HOST)?.to_str().ok()?;
+
+    // drop the port of the plain listener, keeping IPv6 literals intact
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+
+    if https_port == 443 {
+        Some(format!("https://{}{}", host, path))
+    } else {
+        Some(format!("https://{}:{}{}", host, https_port, path))
+    }
+}
+
+/// Serves a plain HTTP listener that permanently redirects every request to HTTPS.
+pub async fn redirect_to_https(listener: TcpListener, https_port: u16, shutdown: Shutdown) {
+    let app = Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        match https_location(&headers, &uri, https_port) {
+            Some(location) => Ok(Redirect::permanent(&location)),
+            None => Err(axum::http::StatusCode::BAD_REQUEST),
+        }
+    });
+
+    if let Err(error) = axum::serve(listener, app)
+        .with_graceful_shutdown(async move { shutdown.wait().await })
+        .await
+    {
+        tracing::error!(%error, "HTTPS redirect listener failed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header, HeaderMap, HeaderValue, Uri};
+
+    use super::https_location;
+
+    #[test]
+    fn test_https_location() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, HeaderValue::from_static("example.com:8080"));
+        let uri: Uri = "/get/key?_token=x".parse().unwrap();
+
+        assert_eq!(
+            https_location(&headers, &uri, 443).unwrap(),
+            "https://example.com/get/key?_token=x"
+        );
+        assert_eq!(
+            https_location(&headers, &uri, 3443).unwrap(),
+            "https://example.com:3443/get/key?_token=x"
+        );
+
+        headers.insert(header::HOST, HeaderValue::from_static("[::1]"));
+        assert_eq!(
+            https_location(&headers, &uri, 443).unwrap(),
+            "https://[::1]/get/key?_token=x"
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use std::{fs, io, os::unix::fs::PermissionsExt, path::Path};
+
+use axum::Router;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::{conn::auto::Builder, graceful::GracefulShutdown},
+    service::TowerToHyperService,
+};
+use tokio::net::UnixListener;
+
+use crate::{config::UnixSocketConfig, shutdown::Shutdown};
+
+/// Binds the socket, replacing a stale socket file left by a previous run, and applies
+/// the configured permissions so only the intended local users can connect.
+pub fn bind(unix_socket: &UnixSocketConfig) -> io::Result<UnixListener> {
+    let path = Path::new(&unix_socket.path);
+
+    if fs::symlink_metadata(path)
+        .is_ok_and(|metadata| std::os::unix::fs::FileTypeExt::is_socket(&metadata.file_type()))
+    {
+        fs::remove_file(path)?;
+    }
+
+    let listener = UnixListener::bind(path)?;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(unix_socket.mode))?;
+
+    Ok(listener)
+}
+
+/// Serves plain HTTP/1 and HTTP/2 on the socket. Requests carry no `ConnectInfo`, so
+/// per IP limits do not apply to local clients. Once the shutdown is triggered the socket
+/// stops accepting and open connections are closed after their in-flight requests.
+pub async fn serve(listener: UnixListener, app: Router, shutdown: Shutdown) {
+    let builder = Builder::new(TokioExecutor::new());
+    let graceful = GracefulShutdown::new();
+
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(error) => {
+                    tracing::error!(%error, "failed to accept Unix socket connection");
+                    continue;
+                }
+            },
+            _ = shutdown.wait() => break,
+        };
+
+        let service = TowerToHyperService::new(app.clone());
+        let connection = builder
+            .serve_connection_with_upgrades(TokioIo::new(stream), service)
+            .into_owned();
+        let connection = graceful.watch(connection);
+
+        tokio::spawn(async move {
+            if let Err(error) = connection.await {
+                tracing::debug!(%error, "Unix socket connection closed with an error");
+            }
+        });
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+}
@@ -1,11 +1,18 @@
 use std::sync::Arc;
 
-use axum::{middleware, Extension, Router};
+use std::time::Duration;
+
+use axum::{
+    extract::DefaultBodyLimit,
+    http::{header, HeaderName, HeaderValue, Method},
+    middleware, Extension, Router,
+};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
 
 use crate::{
     cmd::Args,
-    config::AppConfig,
-    middleware::{check_auth, get_trace_layer},
+    config::{AppConfig, CorsConfig},
+    middleware::{check_auth, get_trace_layer, rate_limit},
     state::AppState,
 };
 
@@ -15,9 +22,75 @@ pub fn app_setup(args: Args) -> (AppConfig, Arc<AppState>) {
     (config, app_state)
 }
 
+/// Builds the CORS layer from a validated configuration, or `None` when no origin is allowed.
+fn cors_layer(config: &CorsConfig) -> Option<CorsLayer> {
+    if !config.is_enabled() {
+        return None;
+    }
+
+    let origins = if config.allows_any_origin() {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            config
+                .allowed_origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+        )
+    };
+
+    let methods = if config.allows_any_method() {
+        AllowMethods::any()
+    } else {
+        AllowMethods::list(
+            config
+                .allowed_methods
+                .iter()
+                .filter_map(|method| Method::from_bytes(method.as_bytes()).ok()),
+        )
+    };
+
+    let headers = if config.allows_any_header() {
+        AllowHeaders::any()
+    } else {
+        AllowHeaders::list(
+            config
+                .allowed_headers
+                .iter()
+                .filter_map(|name| HeaderName::from_bytes(name.as_bytes()).ok()),
+        )
+    };
+
+    Some(
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(methods)
+            .allow_headers(headers)
+            .allow_credentials(config.allow_credentials)
+            .max_age(Duration::from_secs(config.max_age))
+            .expose_headers([header::ETAG, header::CACHE_CONTROL, header::RETRY_AFTER]),
+    )
+}
+
 pub fn add_layers(routes: Router, app_state: Arc<AppState>) -> Router {
-    return routes
+    let body_limit = match app_state.limits.max_body_bytes() {
+        Some(max) => DefaultBodyLimit::max(max),
+        None => DefaultBodyLimit::disable(),
+    };
+
+    let cors = cors_layer(&app_state.cors);
+
+    let routes = routes
+        .layer(body_limit)
         .layer(get_trace_layer())
+        .layer(middleware::from_fn(rate_limit))
         .layer(middleware::from_fn(check_auth))
         .layer(Extension(app_state));
+
+    // Outermost, so preflight requests are answered before authentication and rate
+    // limiting, and rejections still carry the CORS headers browsers need to read them
+    match cors {
+        Some(cors) => routes.layer(cors),
+        None => routes,
+    }
 }
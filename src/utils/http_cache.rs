@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use crate::{config::HttpCacheConfig, models::api_types::SharedRedisPool};
+
+/// Request headers a reply depends on: the token decides the key prefix and permissions,
+/// the encoding headers whether values are base64 encoded.
+const VARY: &str = "Authorization, Upstash-Encoding, Rediserve-Encoding";
+
+/// Remaining time to live of the keys a reply was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyTtl {
+    /// The first of the keys to expire does so after this long
+    Expires(Duration),
+    /// None of the keys expire
+    Persistent,
+    /// A key is missing or its TTL could not be read
+    Unknown,
+}
+
+/// Strong entity tag of a response body.
+pub fn etag(body: &[u8]) -> String {
+    format!("\"{}\"", sha1_smol::Sha1::from(body).digest())
+}
+
+/// Whether an `If-None-Match` header matches the entity tag, using the weak comparison.
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// `Cache-Control` value for a reply: cacheable until its first key expires, capped by the
+/// configured maximum age. Replies of keys without expiry get the maximum age, or must be
+/// revalidated with their ETag when none is configured.
+pub fn cache_control(config: &HttpCacheConfig, ttl: KeyTtl) -> String {
+    let max_age = match (ttl, config.max_age) {
+        (KeyTtl::Expires(ttl), Some(max_age)) => Some(ttl.as_secs().min(max_age)),
+        (KeyTtl::Expires(ttl), None) => Some(ttl.as_secs()),
+        (KeyTtl::Persistent, max_age) => max_age,
+        (KeyTtl::Unknown, _) => None,
+    };
+
+    let visibility = if config.public { "public" } else { "private" };
+
+    match max_age {
+        Some(max_age) => format!("{}, max-age={}", visibility, max_age),
+        None => format!("{}, no-cache", visibility),
+    }
+}
+
+/// Reads the remaining time to live of the keys with PTTL.
+pub async fn key_ttl(pool: &SharedRedisPool, keys: &[String]) -> KeyTtl {
+    let Ok(mut con) = pool.get().await else {
+        return KeyTtl::Unknown;
+    };
+
+    let mut pipeline = redis::pipe();
+    for key in keys {
+        pipeline.cmd("PTTL").arg(key);
+    }
+
+    let ttls: Vec<i64> = match pipeline.query_async(&mut con).await {
+        Ok(ttls) => ttls,
+        Err(_) => return KeyTtl::Unknown,
+    };
+
+    // -1 is a key without expiry, -2 a missing key
+    if ttls.iter().any(|ttl| *ttl < -1) {
+        return KeyTtl::Unknown;
+    }
+
+    match ttls.iter().filter(|ttl| **ttl >= 0).min() {
+        Some(ttl) => KeyTtl::Expires(Duration::from_millis(*ttl as u64)),
+        None => KeyTtl::Persistent,
+    }
+}
+
+/// Answers a successful read with its ETag and `Cache-Control` headers, or with 304 when
+/// the client already holds the same reply. Reads authenticated by a bearer header are never
+/// public, so shared caches do not serve them to other callers.
+pub async fn conditional_response(
+    config: &HttpCacheConfig,
+    pool: &SharedRedisPool,
+    keys: &[String],
+    request_headers: &HeaderMap,
+    body: Vec<u8>,
+) -> Response {
+    let config = HttpCacheConfig {
+        public: config.public && !request_headers.contains_key(header::AUTHORIZATION),
+        ..*config
+    };
+
+    let etag = etag(&body);
+    let cache_control = cache_control(&config, key_ttl(pool, keys).await);
+
+    let headers = [
+        (header::ETAG, etag.clone()),
+        (header::CACHE_CONTROL, cache_control),
+        (header::VARY, VARY.to_string()),
+    ];
+
+    if if_none_match(request_headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, headers).into_response();
+    }
+
+    (
+        headers,
+        [(header::CONTENT_TYPE, "application/json".to_string())],
+        body,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::http::{header, HeaderMap, HeaderValue};
+
+    use super::{cache_control, etag, if_none_match, KeyTtl};
+    use crate::config::HttpCacheConfig;
+
+    #[test]
+    fn test_if_none_match() {
+        let tag = etag(b"{\"result\":\"on\"}");
+        assert!(tag.starts_with('"') && tag.len() == 42);
+
+        let mut headers = HeaderMap::new();
+        assert!(!if_none_match(&headers, &tag));
+
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_str(&format!("\"other\", W/{}", tag)).unwrap(),
+        );
+        assert!(if_none_match(&headers, &tag));
+        assert!(!if_none_match(&headers, &etag(b"{\"result\":\"off\"}")));
+    }
+
+    #[test]
+    fn test_cache_control() {
+        let private = HttpCacheConfig::default();
+        let public = HttpCacheConfig {
+            max_age: Some(300),
+            public: true,
+        };
+        let expires = KeyTtl::Expires(Duration::from_millis(60500));
+
+        assert_eq!(cache_control(&private, expires), "private, max-age=60");
+        assert_eq!(
+            cache_control(&private, KeyTtl::Persistent),
+            "private, no-cache"
+        );
+        assert_eq!(
+            cache_control(&public, KeyTtl::Persistent),
+            "public, max-age=300"
+        );
+        assert_eq!(
+            cache_control(&public, KeyTtl::Expires(Duration::from_secs(3600))),
+            "public, max-age=300"
+        );
+        assert_eq!(cache_control(&public, KeyTtl::Unknown), "public, no-cache");
+    }
+}
@@ -1,5 +1,10 @@
 pub mod app_setup;
+pub mod http_cache;
+pub mod redis_pool;
 pub mod redis_to_json;
 
 pub use app_setup::{add_layers, app_setup};
-pub use redis_to_json::redis_value_to_json;
+pub use redis_to_json::{
+    command_docs_to_json, function_list_to_json, redis_value_to_json, stream_entries_to_json,
+    stream_entry_to_json, stream_read_to_json,
+};
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use crate::models::api_types::{JsonValue, RedisValue};
 use base64::{engine, prelude::*};
 
@@ -30,3 +32,266 @@ pub fn redis_value_to_json(redis_value: RedisValue, encoding: &str) -> JsonValue
         RedisValue::Nil => JsonValue::Null,
     }
 }
+
+/// Converts a stream entry, `[id, [field, value, ...]]`, to `{"id": ..., "fields": {...}}`.
+/// The fields are null for entries deleted while still pending.
+pub fn stream_entry_to_json(entry: RedisValue, encoding: &str) -> JsonValue {
+    let RedisValue::Bulk(mut parts) = entry else {
+        return JsonValue::Null;
+    };
+
+    let fields = match parts.pop() {
+        Some(RedisValue::Bulk(fields)) => {
+            let mut map = serde_json::Map::new();
+            let mut fields = fields.into_iter();
+
+            while let (Some(field), Some(value)) = (fields.next(), fields.next()) {
+                let field = match redis_value_to_json(field, encoding) {
+                    JsonValue::String(field) => field,
+                    field => field.to_string(),
+                };
+                map.insert(field, redis_value_to_json(value, encoding));
+            }
+
+            JsonValue::Object(map)
+        }
+        _ => JsonValue::Null,
+    };
+
+    let id = parts
+        .pop()
+        .map(|id| redis_value_to_json(id, ""))
+        .unwrap_or(JsonValue::Null);
+
+    serde_json::json!({ "id": id, "fields": fields })
+}
+
+/// Converts a list of stream entries as returned by XRANGE or XAUTOCLAIM.
+pub fn stream_entries_to_json(entries: RedisValue, encoding: &str) -> JsonValue {
+    match entries {
+        RedisValue::Bulk(entries) => JsonValue::Array(
+            entries
+                .into_iter()
+                .map(|entry| stream_entry_to_json(entry, encoding))
+                .collect(),
+        ),
+        _ => JsonValue::Array(vec![]),
+    }
+}
+
+/// Converts an XREAD or XREADGROUP reply for a single stream to its list of entries,
+/// empty when the read timed out.
+pub fn stream_read_to_json(reply: RedisValue, encoding: &str) -> JsonValue {
+    let entries = match reply {
+        RedisValue::Bulk(streams) => streams.into_iter().next().and_then(|stream| match stream {
+            RedisValue::Bulk(mut stream) => stream.pop(),
+            _ => None,
+        }),
+        _ => None,
+    };
+
+    stream_entries_to_json(entries.unwrap_or(RedisValue::Nil), encoding)
+}
+
+/// Converts a flat `[name, value, ...]` reply to an object, converting values with `value`.
+fn pairs_to_object(
+    pairs: RedisValue,
+    encoding: &str,
+    value: impl Fn(&str, RedisValue) -> JsonValue,
+) -> JsonValue {
+    let RedisValue::Bulk(pairs) = pairs else {
+        return redis_value_to_json(pairs, encoding);
+    };
+
+    let mut map = serde_json::Map::new();
+    let mut pairs = pairs.into_iter();
+
+    while let (Some(name), Some(item)) = (pairs.next(), pairs.next()) {
+        let name = match redis_value_to_json(name, "") {
+            JsonValue::String(name) => name,
+            name => name.to_string(),
+        };
+        let item = value(&name, item);
+        map.insert(name, item);
+    }
+
+    JsonValue::Object(map)
+}
+
+fn command_doc_to_json(doc: RedisValue) -> JsonValue {
+    pairs_to_object(doc, "", |name, item| match (name, item) {
+        ("arguments", RedisValue::Bulk(arguments)) => {
+            JsonValue::Array(arguments.into_iter().map(command_doc_to_json).collect())
+        }
+        ("subcommands", subcommands) => {
+            pairs_to_object(subcommands, "", |_, doc| command_doc_to_json(doc))
+        }
+        (_, item) => redis_value_to_json(item, ""),
+    })
+}
+
+/// Converts a COMMAND DOCS reply to documents keyed by upper case command name.
+/// Subcommands are taken out of their container's document and keyed as `CONTAINER|SUBCOMMAND`.
+pub fn command_docs_to_json(reply: RedisValue) -> HashMap<String, JsonValue> {
+    let mut docs = HashMap::new();
+
+    let JsonValue::Object(commands) = pairs_to_object(reply, "", |_, doc| command_doc_to_json(doc))
+    else {
+        return docs;
+    };
+
+    for (name, mut doc) in commands {
+        let subcommands = doc
+            .as_object_mut()
+            .and_then(|doc| doc.remove("subcommands"));
+
+        if let Some(JsonValue::Object(subcommands)) = subcommands {
+            for (name, doc) in subcommands {
+                docs.insert(name.to_uppercase(), doc);
+            }
+        }
+
+        docs.insert(name.to_uppercase(), doc);
+    }
+
+    docs
+}
+
+/// Converts a FUNCTION LIST reply to a list of `{library_name, engine, functions, ...}`
+/// objects, each function being a `{name, description, flags}` object.
+pub fn function_list_to_json(reply: RedisValue, encoding: &str) -> JsonValue {
+    let RedisValue::Bulk(libraries) = reply else {
+        return JsonValue::Array(vec![]);
+    };
+
+    JsonValue::Array(
+        libraries
+            .into_iter()
+            .map(|library| {
+                pairs_to_object(library, encoding, |name, item| match (name, item) {
+                    ("functions", RedisValue::Bulk(functions)) => JsonValue::Array(
+                        functions
+                            .into_iter()
+                            .map(|function| {
+                                pairs_to_object(function, encoding, |_, item| {
+                                    redis_value_to_json(item, encoding)
+                                })
+                            })
+                            .collect(),
+                    ),
+                    (_, item) => redis_value_to_json(item, encoding),
+                })
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use redis::Value;
+
+    use super::{
+        command_docs_to_json, function_list_to_json, stream_entry_to_json, stream_read_to_json,
+    };
+
+    fn data(value: &str) -> Value {
+        Value::Data(value.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_stream_entries() {
+        let entry = Value::Bulk(vec![
+            data("1700000000000-0"),
+            Value::Bulk(vec![data("type"), data("signup"), data("user"), data("42")]),
+        ]);
+
+        assert_eq!(
+            stream_entry_to_json(entry.clone(), ""),
+            serde_json::json!({"id": "1700000000000-0", "fields": {"type": "signup", "user": "42"}})
+        );
+
+        let reply = Value::Bulk(vec![Value::Bulk(vec![
+            data("events"),
+            Value::Bulk(vec![
+                entry,
+                Value::Bulk(vec![data("1700000000001-0"), Value::Nil]),
+            ]),
+        ])]);
+
+        let entries = stream_read_to_json(reply, "base64");
+        assert_eq!(entries[0]["id"], "1700000000000-0");
+        assert_eq!(entries[0]["fields"]["dHlwZQ=="], "c2lnbnVw");
+        assert_eq!(entries[1]["fields"], serde_json::Value::Null);
+
+        assert_eq!(stream_read_to_json(Value::Nil, ""), serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_function_list() {
+        let reply = Value::Bulk(vec![Value::Bulk(vec![
+            data("library_name"),
+            data("jobs"),
+            data("engine"),
+            data("LUA"),
+            data("functions"),
+            Value::Bulk(vec![Value::Bulk(vec![
+                data("name"),
+                data("claim"),
+                data("description"),
+                Value::Nil,
+                data("flags"),
+                Value::Bulk(vec![data("no-writes"), data("allow-stale")]),
+            ])]),
+        ])]);
+
+        assert_eq!(
+            function_list_to_json(reply, ""),
+            serde_json::json!([{
+                "library_name": "jobs",
+                "engine": "LUA",
+                "functions": [{
+                    "name": "claim",
+                    "description": null,
+                    "flags": ["no-writes", "allow-stale"]
+                }]
+            }])
+        );
+    }
+
+    #[test]
+    fn test_command_docs() {
+        let reply = Value::Bulk(vec![
+            data("object"),
+            Value::Bulk(vec![
+                data("summary"),
+                data("A container for object introspection commands"),
+                data("subcommands"),
+                Value::Bulk(vec![
+                    data("object|encoding"),
+                    Value::Bulk(vec![
+                        data("since"),
+                        data("2.2.3"),
+                        data("arguments"),
+                        Value::Bulk(vec![Value::Bulk(vec![
+                            data("name"),
+                            data("key"),
+                            data("type"),
+                            data("key"),
+                        ])]),
+                    ]),
+                ]),
+            ]),
+        ]);
+
+        let docs = command_docs_to_json(reply);
+
+        assert_eq!(
+            docs["OBJECT"],
+            serde_json::json!({"summary": "A container for object introspection commands"})
+        );
+        assert_eq!(
+            docs["OBJECT|ENCODING"],
+            serde_json::json!({"since": "2.2.3", "arguments": [{"name": "key", "type": "key"}]})
+        );
+    }
+}
@@ -1,26 +1,233 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use futures::future::join_all;
+
 use crate::{
     cmd::Args,
+    config::{AppConfig, LogFormat},
     routes::app_routes,
+    tls,
     utils::app_setup::{add_layers, app_setup},
 };
 
-pub async fn start_server(args: Args) {
-    tracing_subscriber::fmt()
+/// Binds a TCP listener before its server is spawned, exiting when the address cannot be used.
+async fn bind(addr: SocketAddr) -> tokio::net::TcpListener {
+    match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("Failed to bind {}: {}", addr, error);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn init_logging(config: &AppConfig) {
+    let subscriber = tracing_subscriber::fmt()
         .with_target(false)
-        .compact()
-        .init();
+        .with_max_level(config.logging.level);
 
+    match config.logging.format {
+        LogFormat::Compact => subscriber.compact().init(),
+        LogFormat::Json => subscriber.json().init(),
+    }
+}
+
+pub async fn start_server(args: Args) {
     let (config, app_state) = app_setup(args);
 
+    init_logging(&config);
+
+    if let Some(functions_dir) = &config.functions_dir {
+        match app_state
+            .functions
+            .deploy(&app_state.redis_pool, functions_dir)
+            .await
+        {
+            Ok(libraries) => tracing::info!(?libraries, "deployed function libraries"),
+            Err(error) => {
+                eprintln!("Failed to deploy function libraries: {}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // commands the server describes replace the built-in table, e.g. module commands
+    match app_state.commands.refresh(&app_state.redis_pool).await {
+        Ok(count) => tracing::info!(count, "loaded command metadata from Redis"),
+        Err(error) => {
+            tracing::warn!(%error, "cannot load command metadata, using the built-in table")
+        }
+    }
+
+    app_state.cache.clone().watch(app_state.shutdown.clone());
+
+    // pools of Redis users no token maps to anymore, e.g. after rotating credentials, are dropped
+    app_state
+        .tokens
+        .clone()
+        .watch(Duration::from_secs(config.tokens_reload_interval), {
+            let redis_pools = app_state.redis_pools.clone();
+            let blocking_pools = app_state.blocking_pools.clone();
+            move |tokens| {
+                let users = tokens.redis_users();
+                redis_pools.retain_users(&users);
+                blocking_pools.retain_users(&users);
+            }
+        });
+
     let routes = app_routes();
 
-    let app = add_layers(routes, app_state);
+    let shutdown = app_state.shutdown.clone();
+    shutdown.listen_for_signals();
+
+    let app = add_layers(routes, app_state.clone());
+
+    // every address is bound before any server starts, so one that cannot be used stops the
+    // server before it accepts connections on the others
+    let redirect_port = config.tls.as_ref().and_then(|tls| tls.redirect_port);
+    let mut listeners = vec![];
+    for addr in &config.server_bind {
+        let redirect = match redirect_port {
+            Some(port) => {
+                let redirect_addr = SocketAddr::new(addr.ip(), port);
+                Some((redirect_addr, bind(redirect_addr).await))
+            }
+            None => None,
+        };
+
+        listeners.push((*addr, bind(*addr).await, redirect));
+    }
+
+    let mut servers = vec![];
+
+    // the Unix socket only accepts local clients and is served without TLS
+    #[cfg(unix)]
+    if let Some(unix_socket) = &config.unix_socket {
+        let listener = match crate::unix_socket::bind(unix_socket) {
+            Ok(listener) => listener,
+            Err(error) => {
+                eprintln!("Failed to bind {}: {}", unix_socket.path, error);
+                std::process::exit(1);
+            }
+        };
+
+        println!("Server running on unix:{}", unix_socket.path);
+
+        servers.push(tokio::spawn(crate::unix_socket::serve(
+            listener,
+            app.clone(),
+            shutdown.clone(),
+        )));
+    }
+
+    #[cfg(not(unix))]
+    if config.unix_socket.is_some() {
+        eprintln!("Unix sockets are not supported on this platform");
+        std::process::exit(1);
+    }
+
+    if let Some(tls_config) = &config.tls {
+        let server_config = match tls::load_server_config(tls_config) {
+            Ok(server_config) => server_config,
+            Err(error) => {
+                eprintln!("Failed to load TLS configuration: {}", error);
+                std::process::exit(1);
+            }
+        };
+
+        let rustls_config = RustlsConfig::from_config(Arc::new(server_config));
+
+        tls::watch(rustls_config.clone(), tls_config.clone());
+
+        for (addr, listener, redirect) in listeners {
+            let listener = match listener.into_std() {
+                Ok(listener) => listener,
+                Err(error) => {
+                    eprintln!("Failed to bind {}: {}", addr, error);
+                    std::process::exit(1);
+                }
+            };
+
+            if let Some((redirect_addr, redirect_listener)) = redirect {
+                println!("Redirecting http://{} to https", redirect_addr);
+
+                tokio::spawn(tls::redirect_to_https(
+                    redirect_listener,
+                    addr.port(),
+                    shutdown.clone(),
+                ));
+            }
+
+            println!("Server running on https://{}", addr);
+
+            let handle = Handle::new();
+            tokio::spawn({
+                let handle = handle.clone();
+                let shutdown = shutdown.clone();
+                async move {
+                    shutdown.wait().await;
+                    handle.graceful_shutdown(None);
+                }
+            });
+
+            let server = axum_server::from_tcp(listener)
+                .handle(handle)
+                .acceptor(tls::ClientCertAcceptor::new(rustls_config.clone()))
+                .serve(
+                    app.clone()
+                        .into_make_service_with_connect_info::<SocketAddr>(),
+                );
+
+            servers.push(tokio::spawn(async move {
+                if let Err(error) = server.await {
+                    tracing::error!(%addr, %error, "server failed");
+                }
+            }));
+        }
+    } else {
+        for (addr, listener, _) in listeners {
+            println!("Server running on http://{}", addr);
+
+            let shutdown = shutdown.clone();
+            let server = axum::serve(
+                listener,
+                app.clone()
+                    .into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(async move { shutdown.wait().await });
+
+            servers.push(tokio::spawn(async move {
+                if let Err(error) = server.await {
+                    tracing::error!(%addr, %error, "server failed");
+                }
+            }));
+        }
+    }
+
+    // listeners return once their connections are drained, or the grace period cuts them short
+    let servers = join_all(servers);
+    let grace_period = Duration::from_secs(config.shutdown_grace_period);
 
-    let addr = format!("0.0.0.0:{}", config.server_port);
+    tokio::select! {
+        _ = servers => tracing::info!("all connections drained"),
+        _ = async {
+            shutdown.wait().await;
+            tokio::time::sleep(grace_period).await;
+        } => tracing::warn!(
+            grace_period = config.shutdown_grace_period,
+            "grace period elapsed, closing remaining connections"
+        ),
+    }
 
-    let listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
+    app_state.redis_pools.close();
+    app_state.blocking_pools.close();
 
-    println!("Server running on http://{}", addr);
+    #[cfg(unix)]
+    if let Some(unix_socket) = &config.unix_socket {
+        let _ = std::fs::remove_file(&unix_socket.path);
+    }
 
-    axum::serve(listener, app).await.unwrap();
+    println!("Server stopped");
 }